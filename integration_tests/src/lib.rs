@@ -96,6 +96,79 @@ fn test_is_buf_time_tracking_file_with_empty_buffer_name() {
     assert!(!result, "Buffer with empty name should not be identified as time tracking file");
 }
 
+#[nvim_oxi::test]
+fn test_is_buf_time_tracking_file_with_mapped_extension() {
+    use time_tracking_nvim::options::{self, Options};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let csv_file = create_test_file(temp_dir.path(), "export.csv", "date,task,duration");
+
+    options::set(Options::default());
+    clear_detection_cache();
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&csv_file).unwrap();
+    assert!(!is_buf_time_tracking_file(buf.clone(), &config).unwrap(), "Unmapped extensions are ignored");
+
+    let mut options = Options::default();
+    options.formatters.insert("csv".to_owned(), "Default".to_owned());
+    options::set(options);
+    clear_detection_cache();
+    let result = is_buf_time_tracking_file(buf, &config).unwrap();
+    options::set(Options::default());
+    assert!(result, "Extensions mapped in `formatters` are tracking files");
+}
+
+#[nvim_oxi::test]
+fn test_config_for_extension() {
+    use std::borrow::Cow;
+    use time_tracking_nvim::{config_for_extension, options::{self, Options}};
+
+    let (mut config, _temp_dir) = create_test_config_with_temp_dir();
+    config.formatter = None;
+
+    let mut options = Options::default();
+    options.formatters.insert("csv".to_owned(), "Default".to_owned());
+    options.formatters.insert("txt".to_owned(), "NoSuchFormatter".to_owned());
+    options::set(options);
+
+    let mapped = config_for_extension(&config, Some("csv"));
+    let unmapped = config_for_extension(&config, Some("md"));
+    let unknown = config_for_extension(&config, Some("txt"));
+    options::set(Options::default());
+
+    assert!(matches!(mapped, Cow::Owned(Config { formatter: Some(Formatter::Default), .. })));
+    assert!(matches!(unmapped, Cow::Borrowed(_)), "Unmapped extensions keep the global formatter");
+    assert!(matches!(unknown, Cow::Borrowed(_)), "Unknown formatter names fall back too");
+}
+
+#[nvim_oxi::test]
+fn test_formatter_names() {
+    use time_tracking_nvim::{formatter_names, summary::parse_formatter};
+
+    let names = formatter_names();
+    assert!(names.contains(&"Default"), "Got {:?}", names);
+    for name in names {
+        assert!(parse_formatter(name).is_some(), "'{}' should parse as a formatter", name);
+    }
+}
+
+#[nvim_oxi::test]
+fn test_is_buf_time_tracking_file_with_special_buftype() {
+    use nvim_oxi::api::opts::OptionOptsBuilder;
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let md_file = create_test_file(temp_dir.path(), "test.md", "# Test Content");
+
+    // A scratch buffer whose name points at a real tracking file
+    let mut buf = api::create_buf(false, true).unwrap();
+    buf.set_name(&md_file).unwrap();
+    let opts = OptionOptsBuilder::default().buffer(buf.clone()).build();
+    api::set_option_value("buftype", "nofile", &opts).unwrap();
+
+    let result = is_buf_time_tracking_file(buf, &config).unwrap();
+    assert!(!result, "Special buffers should not be identified as time tracking files");
+}
+
 #[nvim_oxi::test]
 fn test_is_buf_time_tracking_file_in_subdirectory() {
     let (config, temp_dir) = create_test_config_with_temp_dir();
@@ -112,6 +185,71 @@ fn test_is_buf_time_tracking_file_in_subdirectory() {
     assert!(result, "Markdown file in subdirectory of data directory should be identified as time tracking file");
 }
 
+#[nvim_oxi::test]
+fn test_is_buf_time_tracking_file_after_rename() {
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let md_file = create_test_file(temp_dir.path(), "test.md", "# Test Content");
+
+    let other_temp_dir = TempDir::new().expect("Failed to create second temp directory");
+    let other_file = create_test_file(other_temp_dir.path(), "other.md", "# Other Content");
+
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&md_file).unwrap();
+    assert!(is_buf_time_tracking_file(buf.clone(), &config).unwrap());
+
+    // The cached verdict must not survive a rename
+    buf.set_name(&other_file).unwrap();
+    let result = is_buf_time_tracking_file(buf, &config).unwrap();
+    assert!(!result, "Renamed buffer outside the data directory should no longer be identified as time tracking file");
+}
+
+#[nvim_oxi::test]
+fn test_is_buf_time_tracking_file_with_data_directory_as_file() {
+    let (mut config, temp_dir) = create_test_config_with_temp_dir();
+    let not_a_dir = create_test_file(temp_dir.path(), "notes.md", "# Not a directory");
+    config.data_directory = Some(not_a_dir.to_str().unwrap().to_string());
+
+    api::command("lua vim.notify = function(msg) vim.g.tt_last_notify = msg end").unwrap();
+
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&not_a_dir).unwrap();
+
+    let result = is_buf_time_tracking_file(buf, &config).unwrap();
+    assert!(!result, "Nothing should be detected when data_directory is a file");
+
+    let warning: String = api::get_var("tt_last_notify").expect("A warning should be issued");
+    assert!(warning.contains("not a directory"), "Unexpected warning: {}", warning);
+}
+
+#[cfg(windows)]
+#[nvim_oxi::test]
+fn test_is_buf_time_tracking_file_with_extended_length_path() {
+    use std::path::{Path, PathBuf};
+
+    assert_eq!(
+        strip_verbatim_prefix(Path::new(r"\\?\C:\notes\day.md")),
+        PathBuf::from(r"C:\notes\day.md")
+    );
+    assert_eq!(
+        strip_verbatim_prefix(Path::new(r"\\?\UNC\server\share\day.md")),
+        PathBuf::from(r"\\server\share\day.md")
+    );
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let md_file = create_test_file(temp_dir.path(), "test.md", "# Test Content");
+
+    // Name the buffer with the extended-length form canonicalize produces
+    let verbatim = PathBuf::from(format!(r"\\?\{}", md_file.display()));
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&verbatim).unwrap();
+
+    let result = is_buf_time_tracking_file(buf, &config).unwrap();
+    assert!(
+        result,
+        "Extended-length path under the data directory should be identified as time tracking file"
+    );
+}
+
 #[nvim_oxi::test]
 fn test_is_time_tracking_file_current_buffer() {
     let (config, temp_dir) = create_test_config_with_temp_dir();
@@ -164,6 +302,36 @@ fn test_get_buffer_content() {
     assert_eq!(result, expected, "Buffer content should match the set lines joined by newlines");
 }
 
+#[nvim_oxi::test]
+fn test_get_buffer_content_with_custom_join() {
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_lines(0..1, false, vec!["# Test Header", "Some content"]).unwrap();
+    api::set_current_buf(&buf).unwrap();
+
+    assert_eq!(get_buffer_content_with("\r\n").unwrap(), "# Test Header\r\nSome content");
+    assert_eq!(get_buffer_content().unwrap(), "# Test Header\nSome content", "Default join should stay a plain newline");
+}
+
+#[nvim_oxi::test]
+fn test_get_buffer_content_trimmed() {
+    use time_tracking_nvim::options::{self, Options};
+
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_lines(.., false, vec!["", "  ", "# Test Header", "", "Some content", "", ""]).unwrap();
+    api::set_current_buf(&buf).unwrap();
+
+    options::set(Options::default());
+    assert_eq!(get_buffer_content().unwrap(), "\n  \n# Test Header\n\nSome content\n\n", "Untrimmed by default");
+
+    options::set(Options {
+        trim_content: true,
+        ..Options::default()
+    });
+    let trimmed = get_buffer_content().unwrap();
+    options::set(Options::default());
+    assert_eq!(trimmed, "# Test Header\n\nSome content", "Interior blank lines should be kept");
+}
+
 #[nvim_oxi::test]
 fn test_get_buffer_content_empty() {
     // Create an empty buffer
@@ -175,6 +343,22 @@ fn test_get_buffer_content_empty() {
     assert_eq!(result, "", "Empty buffer should return empty string");
 }
 
+#[nvim_oxi::test]
+fn test_get_buffer_content_with_invalid_utf8() {
+    // Create a buffer with a line containing a byte that isn't valid UTF-8
+    let mut buf = api::create_buf(false, false).unwrap();
+    let lines = vec![
+        nvim_oxi::String::from("# Header"),
+        nvim_oxi::String::from_bytes(b"- Task \xff: 1h"),
+    ];
+    buf.set_lines(.., false, lines).unwrap();
+    api::set_current_buf(&buf).unwrap();
+
+    // Test the function - should convert lossily instead of failing
+    let result = get_buffer_content().unwrap();
+    assert_eq!(result, "# Header\n- Task \u{FFFD}: 1h", "Invalid bytes should be replaced, not dropped or panicked on");
+}
+
 #[nvim_oxi::test]
 fn test_any_tracking_visible_with_tracking_window() {
     let (config, temp_dir) = create_test_config_with_temp_dir();
@@ -198,9 +382,10 @@ fn test_any_tracking_visible_with_tracking_window() {
 fn test_any_tracking_visible_with_preview_window() {
     let (config, _temp_dir) = create_test_config_with_temp_dir();
     
-    // Create a buffer that looks like a preview window
+    // Create a buffer that is marked as the preview
     let mut buf = api::create_buf(false, false).unwrap();
     buf.set_name("some/path/[Time Tracking Preview]").unwrap();
+    buf.set_var(PREVIEW_VAR, true).unwrap();
     
     let mut win = api::get_current_win();
     win.set_buf(&buf).unwrap();
@@ -305,6 +490,52 @@ fn test_create_or_update_preview_with_empty_output() {
     assert!(preview_buffer.is_some(), "Preview buffer should be created even with empty content");
 }
 
+#[nvim_oxi::test]
+fn test_create_or_update_preview_fires_user_events() {
+    api::command("autocmd User TimeTrackingPreviewUpdated let g:tt_preview_updated = 1").unwrap();
+    api::command("autocmd User TimeTrackingPreviewOpened let g:tt_preview_opened = 1").unwrap();
+
+    create_or_update_preview("# Test Content").unwrap();
+
+    let updated: i64 = api::get_var("tt_preview_updated").unwrap();
+    assert_eq!(updated, 1, "Updating the preview should fire TimeTrackingPreviewUpdated");
+    let opened: i64 = api::get_var("tt_preview_opened").unwrap();
+    assert_eq!(opened, 1, "Opening the preview split should fire TimeTrackingPreviewOpened");
+}
+
+#[nvim_oxi::test]
+fn test_apply_render_drops_stale_generations() {
+    use time_tracking_nvim::{apply_render, next_render_generation};
+
+    // Two renders requested, completing out of order
+    let older = next_render_generation();
+    let newer = next_render_generation();
+
+    assert!(apply_render(newer, "# Newer").unwrap(), "Latest render should be applied");
+    assert!(!apply_render(older, "# Older").unwrap(), "Stale render should be dropped");
+
+    let preview_buffer = api::list_bufs().find(|buf| {
+        buf.get_name().map(|name| name.ends_with("[Time Tracking Preview]")).unwrap_or(false)
+    }).expect("Preview buffer should exist");
+    let lines: Vec<String> = preview_buffer.get_lines(.., false).unwrap()
+        .map(|s| s.to_string_lossy().into())
+        .collect();
+    assert_eq!(lines.join("\n"), "# Newer", "Stale render must not overwrite newer content");
+}
+
+#[nvim_oxi::test]
+fn test_update_from_inside_preview_does_not_split() {
+    create_or_update_preview("# Test Content").unwrap();
+    let preview = find_preview_windows().unwrap().pop().expect("Preview window should be open");
+    api::set_current_win(&preview).unwrap();
+    let window_count = api::list_wins().count();
+
+    create_or_update_preview("# Updated").unwrap();
+
+    assert_eq!(api::list_wins().count(), window_count, "Updating from the preview must not split it");
+    assert_eq!(find_preview_windows().unwrap().len(), 1);
+}
+
 #[nvim_oxi::test]
 fn test_create_or_update_preview_buffer_options() {
     let test_output = "# Test Content";
@@ -357,6 +588,56 @@ fn test_time_tracking_with_config_creates_commands() {
         "TimeTrackingAutoClose",
         "TimeTrackingClose",
         "TimeTrackingMaybeCloseIfInvisible",
+        "TimeTrackingWipePreview",
+        "TimeTrackingInsertHeader",
+        "TimeTrackingSetPrefix",
+        "TimeTrackingSetSuffix",
+        "TimeTrackingPin",
+        "TimeTrackingUnpin",
+        "TimeTrackingWeek",
+        "TimeTrackingCompare",
+        "TimeTrackingToggleView",
+        "TimeTrackingRestoreTab",
+        "TimeTrackingConfig",
+        "TimeTrackingScratch",
+        "TimeTrackingPause",
+        "TimeTrackingResume",
+        "TimeTrackingReload",
+        "TimeTrackingProjects",
+        "TimeTrackingSigns",
+        "TimeTrackingUpdateSigns",
+        "TimeTrackingClearSigns",
+        "TimeTrackingCloseAll",
+        "TimeTrackingOpenDataDir",
+        "TimeTrackingGotoEntry",
+        "TimeTrackingAutoCreateToday",
+        "TimeTrackingPreviewFocus",
+        "TimeTrackingPreviewBlur",
+        "TimeTrackingRange",
+        "TimeTrackingYTD",
+        "TimeTrackingPrint",
+        "TimeTrackingNotifyOnSave",
+        "TimeTrackingSort",
+        "TimeTrackingResizePreview",
+        "TimeTrackingExportCsv",
+        "TimeTrackingExportJson",
+        "TimeTrackingUpdateDebounced",
+        "TimeTrackingZoom",
+        "TimeTrackingUndoClose",
+        "TimeTrackingRelativeDates",
+        "TimeTrackingFiles",
+        "TimeTrackingCarryOver",
+        "TimeTrackingSectionTotals",
+        "TimeTrackingEdit",
+        "TimeTrackingExportOnExit",
+        "TimeTrackingDelta",
+        "TimeTrackingFormatters",
+        "TimeTrackingUpdateInline",
+        "TimeTrackingClearInline",
+        "TimeTrackingRunCli",
+        "TimeTrackingCopyEntry",
+        "TimeTrackingSeconds",
+        "TimeTrackingReset",
     ];
     
     for cmd in commands_to_test {
@@ -484,4 +765,1645 @@ fn test_multiple_preview_creation_updates_same_buffer() {
         .collect();
     let content = lines.join("\n");
     assert_eq!(content, content3, "Should have the latest content");
-}
\ No newline at end of file
+}
+#[nvim_oxi::test]
+fn test_format_number_grouping() {
+    use time_tracking_nvim::{entries::{format_delta, format_duration}, labels::format_number, options::{self, Options}};
+
+    // Default: no grouping, matching the formatter's own output
+    assert_eq!(format_number(1234567), "1234567");
+    assert_eq!(format_duration(1234 * 60 + 5), "1234h5m");
+
+    options::set(Options { number_locale: Some("en_US".to_string()), ..Options::default() });
+    assert_eq!(format_number(1234567), "1,234,567");
+    assert_eq!(format_number(-1234), "-1,234");
+    assert_eq!(format_number(999), "999");
+    assert_eq!(format_duration(1234 * 60 + 5), "1,234h5m", "Plugin-computed durations group their hours");
+    assert_eq!(format_delta(-1500 * 3600), "-1,500h");
+    assert_eq!(format_duration(45), "45m");
+
+    options::set(Options { number_locale: Some("de".to_string()), ..Options::default() });
+    assert_eq!(format_number(1234), "1.234");
+    options::set(Options::default());
+}
+
+#[nvim_oxi::test]
+fn test_summarize_has_no_window_side_effects() {
+    let (config, _temp_dir) = create_test_config_with_temp_dir();
+    let content = "# 2024-01-01\n- Task 1: 2h\n- Task 2: 30m";
+
+    let windows_before = api::list_wins().len();
+    let buffers_before = api::list_bufs().len();
+
+    let summary = time_tracking_nvim::summarize(content, &config);
+
+    // Same output as calling the formatter directly
+    let expected = config.get_formatter().day_summary(content, "", config.get_prefix(), config.get_suffix());
+    assert_eq!(summary, expected, "summarize should match the formatter's day summary");
+
+    // Deterministic, and nothing opened or created
+    assert_eq!(time_tracking_nvim::summarize(content, &config), summary);
+    assert_eq!(api::list_wins().len(), windows_before, "summarize should not open windows");
+    assert_eq!(api::list_bufs().len(), buffers_before, "summarize should not create buffers");
+}
+
+#[nvim_oxi::test]
+fn test_resolve_date_tokens() {
+    use time::{Date, Month};
+    use time_tracking_nvim::dates::resolve_date;
+
+    let today = Date::from_calendar_date(2024, Month::March, 1).unwrap();
+    let feb = |day| Date::from_calendar_date(2024, Month::February, day).unwrap();
+
+    assert_eq!(resolve_date("today", today), Some(today));
+    assert_eq!(resolve_date("yesterday", today), Some(feb(29)));
+    assert_eq!(resolve_date("-1", today), Some(feb(29)));
+    assert_eq!(resolve_date("-7", today), Some(feb(23)));
+    assert_eq!(resolve_date("+1", today), Some(Date::from_calendar_date(2024, Month::March, 2).unwrap()));
+    assert_eq!(resolve_date("2024-02-10", today), Some(feb(10)));
+    assert_eq!(resolve_date("2024-13-01", today), None, "Invalid months should be rejected");
+    assert_eq!(resolve_date("someday", today), None, "Unknown tokens should be rejected");
+}
+
+#[nvim_oxi::test]
+fn test_tracked_dates_follow_daily_file_pattern() {
+    use time::{Date, Month};
+    use time_tracking_nvim::{files::{daily_file_path, tracked_dates}, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options { daily_file_pattern: "%Y/%m/%Y-%m-%d.md".to_string(), ..Options::default() });
+
+    create_test_file(temp_dir.path(), "2024/01/2024-01-02.md", "# Day 2");
+    create_test_file(temp_dir.path(), "2024/01/2024-01-05.md", "# Day 5");
+    create_test_file(temp_dir.path(), "2024/01/notes.md", "# Not a daily file");
+
+    let jan = |day| Date::from_calendar_date(2024, Month::January, day).unwrap();
+    assert_eq!(tracked_dates(&config), vec![jan(5), jan(2)], "Dates should be recovered from paths, newest first");
+
+    let expected = fs::canonicalize(temp_dir.path()).unwrap().join("2024/01/2024-01-05.md");
+    assert_eq!(daily_file_path(&config, jan(5)), Some(expected));
+}
+
+#[nvim_oxi::test]
+fn test_update_events_accept_cursor_hold() {
+    use nvim_oxi::{Array, Dictionary, Object};
+    use time_tracking_nvim::options::Options;
+
+    assert_eq!(Options::default().update_events(), vec!["TextChanged", "TextChangedI"]);
+    let low_power = Options { low_power: true, ..Options::default() };
+    assert_eq!(low_power.update_events(), vec!["InsertLeave", "BufWritePost"]);
+
+    let events = Array::from_iter(["CursorHoldI", "CursorHold", "KeyPressed"].map(Object::from));
+    let options = Options::from_dict(&Dictionary::from_iter([("update_events", Object::from(events))]));
+    assert_eq!(options.update_events(), vec!["CursorHold", "CursorHoldI"], "Configured events replace the preset");
+    assert_eq!(options.unknown_update_events(), vec!["KeyPressed"]);
+}
+
+#[nvim_oxi::test]
+fn test_create_new_file_never_clobbers() {
+    use time_tracking_nvim::files::create_new_file;
+
+    // Two instances racing to create the same day: the second finds it there
+    let (_config, temp_dir) = create_test_config_with_temp_dir();
+    let path = temp_dir.path().join("2024/01/2024-01-02.md");
+    assert!(create_new_file(&path, "# First instance\n").unwrap(), "The first creation writes the file");
+    assert!(!create_new_file(&path, "# Second instance\n").unwrap(), "A second creation is a no-op");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "# First instance\n");
+}
+
+#[nvim_oxi::test]
+fn test_wipe_preview_buffers_only_wipes_own_preview() {
+    use time_tracking_nvim::wipe_preview_buffers;
+
+    create_or_update_preview("# Test Content").unwrap();
+    let preview = find_preview_buffer().unwrap().expect("Preview buffer should exist");
+    preview.clone().set_name("[Time Tracking Preview] (renamed)").unwrap();
+
+    // Named like the preview, but not marked as one
+    let mut lookalike = api::create_buf(true, true).unwrap();
+    lookalike.set_name("[Time Tracking Preview]").unwrap();
+
+    wipe_preview_buffers().unwrap();
+    assert!(!preview.is_valid(), "This instance's preview should be wiped, whatever its name");
+    assert!(lookalike.is_valid(), "A same-named buffer without the marker should survive");
+}
+
+#[nvim_oxi::test]
+fn test_tracking_files_lists_tracking_extensions() {
+    use time_tracking_nvim::{files::tracking_files, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    create_test_file(temp_dir.path(), "2024-01-02.md", "# Day 2");
+    create_test_file(temp_dir.path(), "2024/01/2024-01-05.md", "# Day 5");
+    create_test_file(temp_dir.path(), "notes.txt", "Not tracked");
+    create_test_file(temp_dir.path(), "export.csv", "date,task,duration");
+
+    let mut options = Options::default();
+    options.formatters.insert("csv".to_owned(), "Default".to_owned());
+    options::set(options);
+    let files = tracking_files(&config);
+    options::set(Options::default());
+
+    let root = fs::canonicalize(temp_dir.path()).unwrap();
+    assert_eq!(files, vec![
+        root.join("2024/01/2024-01-05.md"),
+        root.join("2024-01-02.md"),
+        root.join("export.csv"),
+    ], "Markdown and mapped extensions should be listed recursively, sorted");
+}
+
+#[nvim_oxi::test]
+fn test_directory_listing_is_reused_until_invalidated() {
+    use time_tracking_nvim::files::{create_new_file, invalidate_listing, tracking_files};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let root = fs::canonicalize(temp_dir.path()).unwrap();
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# Day 1");
+    assert_eq!(tracking_files(&config).len(), 1);
+
+    // Written behind the plugin's back: the listing isn't re-read this soon
+    create_test_file(temp_dir.path(), "2024-01-02.md", "# Day 2");
+    assert_eq!(tracking_files(&config).len(), 1, "An unchanged listing shouldn't be re-read right away");
+
+    invalidate_listing();
+    assert_eq!(tracking_files(&config).len(), 2, "Invalidating should re-read the directory");
+
+    // Files the plugin creates itself show up immediately
+    create_new_file(&root.join("2024-01-03.md"), "# Day 3").unwrap();
+    assert_eq!(tracking_files(&config).len(), 3);
+}
+
+#[nvim_oxi::test]
+fn test_tracking_files_by_date_newest_first() {
+    use time_tracking_nvim::files::tracking_files_by_date;
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    create_test_file(temp_dir.path(), "2024-01-05.md", "# Day 5");
+    create_test_file(temp_dir.path(), "2024-03-01.md", "# Day 1");
+    create_test_file(temp_dir.path(), "2024-01-20.md", "# Day 20");
+    create_test_file(temp_dir.path(), "notes.md", "# Not a daily file");
+    create_test_file(temp_dir.path(), "backlog.md", "# Not a daily file");
+
+    let root = fs::canonicalize(temp_dir.path()).unwrap();
+    assert_eq!(tracking_files_by_date(&config), vec![
+        root.join("2024-03-01.md"),
+        root.join("2024-01-20.md"),
+        root.join("2024-01-05.md"),
+        root.join("backlog.md"),
+        root.join("notes.md"),
+    ], "Daily files should come newest first, then undated files by path");
+}
+
+#[nvim_oxi::test]
+fn test_carry_over_lines_skips_existing() {
+    use time_tracking_nvim::entries::carry_over_lines;
+
+    let yesterday = "# 2024-01-01\n\n- Standup: 15m\n  * Code review 1h30m\n09:00-10:15 Deep work\nJust a note";
+    let today = vec!["# 2024-01-02".to_string(), "- Standup: 15m".to_string()];
+
+    assert_eq!(carry_over_lines(yesterday, &today, false), vec![
+        "  * Code review 1h30m",
+        "09:00-10:15 Deep work",
+    ], "Entries already present and non-entry lines are skipped");
+
+    let today = vec!["# 2024-01-02".to_string(), "- Standup: 0m".to_string()];
+    assert_eq!(carry_over_lines(yesterday, &today, true), vec![
+        "  * Code review: 0m",
+        "Deep work: 0m",
+    ], "Zeroed entries keep their indentation and list marker");
+}
+
+#[nvim_oxi::test]
+fn test_section_subtotals_update_in_place() {
+    use time_tracking_nvim::{entries::{parse_entries, with_section_subtotals}, options::{self, Options}};
+
+    let lines: Vec<String> = "# 2024-01-01\n## Work\n- coding 1h\n- review 30m\n\n## Home\n- notes\n## Errands\n- shopping 45m"
+        .lines().map(String::from).collect();
+    let annotated = with_section_subtotals(&lines);
+    assert_eq!(annotated, vec![
+        "# 2024-01-01", "## Work", "- coding 1h", "- review 30m", "Subtotal: 1h30m", "",
+        "## Home", "- notes", "## Errands", "- shopping 45m", "Subtotal: 45m",
+    ], "Sections with entries get a subtotal after their last line");
+    assert_eq!(parse_entries(&annotated.join("\n")).len(), 3, "Subtotals should not parse as entries");
+
+    let mut edited = annotated.clone();
+    edited[3] = "- review 1h".to_string();
+    let updated = with_section_subtotals(&edited);
+    assert_eq!(updated[4], "Subtotal: 2h", "Re-running should update the existing subtotal");
+    assert_eq!(updated.len(), annotated.len(), "Subtotals should not stack");
+
+    options::set(Options {
+        i18n: [("total".to_string(), "Gesamt".to_string())].into_iter().collect(),
+        ..Options::default()
+    });
+    let relabeled = with_section_subtotals(&updated);
+    options::set(Options::default());
+    assert_eq!(relabeled, updated, "Changing labels should not stack subtotals");
+}
+
+#[nvim_oxi::test]
+fn test_overlapping_ranges_warn() {
+    use time_tracking_nvim::{options::{self, Options}, overlap_warnings};
+
+    let overlapping = "# 2024-01-01\n- standup 09:00-10:00\n- review 09:30-10:30\n- lunch 10:30-11:00";
+    let separate = "# 2024-01-01\n- standup 09:00-10:00\n- review 10:00-10:30\n- notes 1h";
+
+    assert_eq!(overlap_warnings(overlapping), None, "Off unless warn_overlaps is set");
+
+    options::set(Options { warn_overlaps: true, ..Options::default() });
+    let warned = overlap_warnings(overlapping);
+    let clean = overlap_warnings(separate);
+    options::set(Options::default());
+
+    assert_eq!(
+        warned.as_deref(),
+        Some("⚠ Overlapping time ranges: line 2 (09:00-10:00), line 3 (09:30-10:30)"),
+        "Only the overlapping pair is reported; touching ranges are fine"
+    );
+    assert_eq!(clean, None, "Back-to-back ranges don't overlap");
+}
+
+#[nvim_oxi::test]
+fn test_show_seconds_in_computed_durations() {
+    use time_tracking_nvim::{
+        billable_footer, entries::{format_delta, format_precise, parse_entries, total_minutes, total_seconds},
+        options::{self, Options}, state,
+    };
+
+    let entries = parse_entries("# 2024-01-01\n- timer 1h30m15s\n- sync 45s\n- review 30m");
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[0].minutes, entries[0].seconds), (90, 15));
+    assert_eq!(entries[0].description, "timer");
+    assert_eq!(total_minutes(&entries), 120, "Minute totals still truncate the seconds");
+
+    assert_eq!(total_seconds(&entries), 7260);
+    let timer = entries[0].duration_seconds();
+    assert_eq!(format_precise(timer), "1h30m", "Minute precision by default");
+
+    assert_eq!(format_delta(45), "±0m", "Deltas under a minute show as none by default");
+
+    options::set(Options { billable_marker: Some("timer".to_string()), ..Options::default() });
+    state::with(|s| s.show_seconds = true);
+    let precise = format_precise(timer);
+    let whole = format_precise(total_seconds(&entries));
+    let zero = format_precise(0);
+    let delta = format_delta(45);
+    let footer = billable_footer(&entries);
+    state::with(|s| s.show_seconds = false);
+    options::set(Options::default());
+    assert_eq!(precise, "1h30m15s");
+    assert_eq!(whole, "2h1m", "Zero parts are left out");
+    assert_eq!(zero, "0s");
+    assert_eq!(delta, "+45s");
+    assert_eq!(footer.as_deref(), Some("Billable: 1h30m15s\nNon-billable: 30m45s\nTotal: 2h1m"), "Footers keep the seconds");
+}
+
+#[nvim_oxi::test]
+fn test_parse_hook_replaces_builtin_parser() {
+    use time_tracking_nvim::{entries::{parse_day, parse_entries}, options::{self, Options}, render_day_summary};
+
+    api::command(concat!(
+        r#"lua package.loaded["time-tracking-nvim"] = { parse_hook = function(content) "#,
+        r#"local entries = {} "#,
+        r#"for name, mins in content:gmatch("(%w+)=(%d+)") do "#,
+        r#"table.insert(entries, { description = name, minutes = tonumber(mins) }) end "#,
+        r#"table.insert(entries, { "positional", 15 }) "#,
+        r#"return entries end }"#,
+    )).unwrap();
+
+    let (config, _temp_dir) = create_test_config_with_temp_dir();
+    let content = "# 2024-01-01\ncoding=90 review=30";
+    options::set(Options { parse_hook: true, ..Options::default() });
+    let day = parse_day(content);
+    let summary = render_day_summary(&config, content, &day);
+    options::set(Options::default());
+
+    assert!(day.hooked);
+    let parsed: Vec<(&str, i64, usize)> = day.entries.iter()
+        .map(|e| (e.description.as_str(), e.minutes, e.line))
+        .collect();
+    assert_eq!(parsed, vec![("coding", 90, 1), ("review", 30, 1), ("positional", 15, 0)]);
+    assert_eq!(summary, "# 2024-01-01\n\n- coding: 1h30m\n- review: 30m\n- positional: 15m\n\nTotal: 2h15m");
+
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = { parse_hook = function() error("boom") end }"#).unwrap();
+    options::set(Options { parse_hook: true, ..Options::default() });
+    let fallback = parse_entries("# 2024-01-01\n- coding 1h");
+    options::set(Options::default());
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = nil"#).unwrap();
+    assert_eq!(fallback.len(), 1, "A failing hook should fall back to the built-in parser");
+}
+
+#[nvim_oxi::test]
+fn test_parse_hook_drops_out_of_range_lines() {
+    use time_tracking_nvim::{entries::{carry_over_lines, parse_entries}, options::{self, Options}};
+
+    api::command(concat!(
+        r#"lua package.loaded["time-tracking-nvim"] = { parse_hook = function() return { "#,
+        r#"{ description = "past end", minutes = 10, line = 999 }, "#,
+        r#"{ description = "zero", minutes = 10, line = 0 }, "#,
+        r#"{ description = "kept", minutes = 20, line = 2 } } end }"#,
+    )).unwrap();
+
+    let content = "# 2024-01-01\n- kept 20m";
+    options::set(Options { parse_hook: true, ..Options::default() });
+    let entries = parse_entries(content);
+    let carried = carry_over_lines(content, &[], false);
+    options::set(Options::default());
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = nil"#).unwrap();
+
+    let parsed: Vec<(&str, usize)> = entries.iter().map(|e| (e.description.as_str(), e.line)).collect();
+    assert_eq!(parsed, vec![("kept", 1)], "Hook entries outside the content should be dropped");
+    assert_eq!(carried, vec!["- kept 20m".to_owned()]);
+}
+
+#[nvim_oxi::test]
+fn test_parse_hook_on_empty_buffer() {
+    use time_tracking_nvim::{entries::{carry_over_lines, parse_entries}, options::{self, Options}, render_preview_content};
+
+    api::command(concat!(
+        r#"lua package.loaded["time-tracking-nvim"] = { parse_hook = function() "#,
+        r#"return { { description = "coding", minutes = 30 } } end }"#,
+    )).unwrap();
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    let md_file = create_test_file(temp_dir.path(), "2024-01-01.md", "");
+    api::command(&format!("edit {}", md_file.display())).unwrap();
+
+    options::set(Options { parse_hook: true, group_by_tag: true, ..Options::default() });
+    let entries = parse_entries("");
+    let carried = carry_over_lines("", &[], false);
+    let rendered = render_preview_content(config_static);
+    options::set(Options::default());
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = nil"#).unwrap();
+
+    assert!(entries.is_empty(), "An entry with nowhere to go should be dropped");
+    assert!(carried.is_empty());
+    assert!(rendered.is_ok(), "Rendering an empty buffer should not fail");
+}
+
+#[nvim_oxi::test]
+fn test_parse_hook_called_once_per_render() {
+    use time_tracking_nvim::{options::{self, Options}, render_preview_content};
+
+    api::command(concat!(
+        r#"lua package.loaded["time-tracking-nvim"] = { calls = 0, parse_hook = function() "#,
+        r#"local m = package.loaded["time-tracking-nvim"] m.calls = m.calls + 1 "#,
+        r#"return { { description = "coding #dev", minutes = 60, line = 2 } } end }"#,
+    )).unwrap();
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    let md_file = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding #dev 1h");
+    api::command(&format!("edit {}", md_file.display())).unwrap();
+
+    options::set(Options {
+        parse_hook: true,
+        group_by_tag: true,
+        billable_marker: Some("$".to_string()),
+        warn_overlaps: true,
+        daily_budget_minutes: 30,
+        ..Options::default()
+    });
+    let output = render_preview_content(config_static).unwrap();
+    options::set(Options::default());
+    let calls: i64 = api::call_function("luaeval", (r#"package.loaded["time-tracking-nvim"].calls"#,)).unwrap();
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = nil"#).unwrap();
+
+    assert!(output.contains("## #dev (1h)"), "Tags should come from the hook's entries: {output}");
+    assert_eq!(calls, 1, "A render should call the parse_hook once");
+}
+
+#[nvim_oxi::test]
+fn test_parse_entries_durations_and_ranges() {
+    use time_tracking_nvim::entries::{format_duration, parse_entries, total_minutes};
+
+    let content = "# 2024-01-01\n\n- Standup: 15m\n- Code review 1h30m\n- Planning: 1.5h\n09:00-10:15 Deep work\nJust a note";
+    let entries = parse_entries(content);
+
+    let parsed: Vec<(usize, &str, i64)> = entries.iter().map(|e| (e.line, e.description.as_str(), e.minutes)).collect();
+    assert_eq!(parsed, vec![
+        (2, "Standup", 15),
+        (3, "Code review", 90),
+        (4, "Planning", 90),
+        (5, "Deep work", 75),
+    ], "Headings and notes should be skipped, durations and ranges parsed");
+
+    assert_eq!(format_duration(total_minutes(&entries)), "4h30m");
+}
+
+#[nvim_oxi::test]
+fn test_ignore_prefix_skips_note_lines() {
+    use time_tracking_nvim::{entries::{parse_entries, total_minutes}, options::{self, Options}};
+
+    let content = "# 2024-01-01\n- coding 1h\n// lunch break 45m\n  // call back at 14:00-14:30\n> blocked 20m\n- review 30m";
+    assert_eq!(total_minutes(&parse_entries(content)), 185, "Without a prefix every line may be an entry");
+
+    options::set(Options { ignore_prefix: Some("//".to_string()), ..Options::default() });
+    let entries = parse_entries(content);
+    options::set(Options::default());
+
+    let descriptions: Vec<&str> = entries.iter().map(|e| e.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["coding", "> blocked", "review"], "Indented note lines are skipped too");
+    assert_eq!(total_minutes(&entries), 110);
+}
+
+#[nvim_oxi::test]
+fn test_render_tag_groups_with_subtotals() {
+    use regex::Regex;
+    use time_tracking_nvim::{entries::parse_day, tags::{group_summary_by_tag, render_tag_groups}};
+
+    let content = "# 2024-01-01\n- standup #meeting 15m\n- coding #dev 1h30m\n- design review #dev #meeting 30m\n- email 20m";
+    let day = parse_day(content);
+    let groups = render_tag_groups(&day, &Regex::new(r"#[\w-]+").unwrap());
+    assert_eq!(groups, "## #dev (2h)\n- coding #dev: 1h30m\n- design review #dev #meeting: 30m (also #meeting)\n\n\
+        ## #meeting (45m)\n- standup #meeting: 15m\n- design review #dev #meeting: 30m (also #dev)\n\n\
+        ## Untagged (20m)\n- email: 20m");
+
+    let grouped = group_summary_by_tag("# Monday\n\nformatter output", &day);
+    assert!(grouped.starts_with("# Monday\n\n## #dev (2h)"), "The formatter's heading should be kept: {grouped}");
+    assert!(grouped.ends_with("Total: 2h35m"), "Entries with several tags count once in the total: {grouped}");
+}
+
+#[nvim_oxi::test]
+fn test_seconds_by_project_groups_tags() {
+    use regex::Regex;
+    use time_tracking_nvim::projects::seconds_by_project;
+
+    let content = "# 2024-01-01\n- [ProjX] design review 1h\n- [ProjY] planning 30m\n- [ProjX] coding 09:00-10:30\n- email 15m";
+    let projects = seconds_by_project(content, &Regex::new(r"\[([^\]]+)\]").unwrap());
+
+    assert_eq!(projects.get(&Some("ProjX".to_string())), Some(&(150 * 60)));
+    assert_eq!(projects.get(&Some("ProjY".to_string())), Some(&(30 * 60)));
+    assert_eq!(projects.get(&None), Some(&(15 * 60)), "Entries without a tag should be untagged");
+}
+
+#[nvim_oxi::test]
+fn test_billable_footer_splits_marked_entries() {
+    use time_tracking_nvim::{billable_footer, entries::parse_entries, options::{self, Options}};
+
+    let entries = parse_entries("# 2024-01-01\n- $ client call 1h\n- review for $acme 30m\n- internal sync 45m");
+
+    options::set(Options::default());
+    assert_eq!(billable_footer(&entries), None, "No footer without a billable_marker");
+
+    options::set(Options {
+        billable_marker: Some("$".to_string()),
+        ..Options::default()
+    });
+    assert_eq!(
+        billable_footer(&entries).as_deref(),
+        Some("Billable: 1h30m\nNon-billable: 45m\nTotal: 2h15m")
+    );
+}
+
+#[nvim_oxi::test]
+fn test_week_totals_reports_zero_for_missing_days() {
+    use time_tracking_nvim::{aggregate::week_totals, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h");
+    create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- review 30m");
+
+    // The test config starts weeks on Saturday
+    let wednesday = time::Date::from_calendar_date(2024, time::Month::January, 3).unwrap();
+    let totals = week_totals(&config, wednesday);
+
+    let minutes: Vec<i64> = totals.iter().map(|(_, minutes)| *minutes).collect();
+    assert_eq!(minutes, vec![0, 0, 60, 0, 30, 0, 0]);
+    assert_eq!(totals[0].0, time::Date::from_calendar_date(2023, time::Month::December, 30).unwrap());
+}
+
+#[nvim_oxi::test]
+fn test_week_start_day_falls_back_to_monday() {
+    use time_tracking_nvim::aggregate::week_start_day;
+
+    let (mut config, _temp_dir) = create_test_config_with_temp_dir();
+    assert_eq!(week_start_day(&config), time::Weekday::Saturday);
+
+    config.week_start_day = Some("sun".to_string());
+    assert_eq!(week_start_day(&config), time::Weekday::Sunday);
+
+    config.week_start_day = Some("Sundy".to_string());
+    assert_eq!(week_start_day(&config), time::Weekday::Monday, "Misspellings fall back to Monday");
+
+    config.week_start_day = None;
+    assert_eq!(week_start_day(&config), time::Weekday::Monday);
+}
+
+#[nvim_oxi::test]
+fn test_task_delta_since_last_change() {
+    use time_tracking_nvim::{aggregate::{seconds_by_task, record_task_snapshot, render_task_delta}, entries::parse_entries, state};
+
+    let tasks = |content: &str| seconds_by_task(&parse_entries(content));
+    record_task_snapshot(1, tasks("- coding 1h\n- email 15m"));
+    record_task_snapshot(1, tasks("- coding 1h\n- email 15m"));
+    assert!(state::with(|s| s.task_snapshots.as_ref().unwrap().previous.is_none()), "Unchanged refreshes are not snapshots");
+
+    record_task_snapshot(1, tasks("- Coding 1h30m\n- review 20m"));
+    let (before, after) = state::with(|s| {
+        let snapshots = s.task_snapshots.as_ref().unwrap();
+        (snapshots.previous.clone().unwrap(), snapshots.latest.clone())
+    });
+    assert_eq!(render_task_delta(&before, &after), "~ Coding: 1h → 1h30m (+30m)\n+ review: 20m\n- email: 15m\nDelta: +35m");
+
+    record_task_snapshot(2, tasks("- coding 1h"));
+    assert!(state::with(|s| s.task_snapshots.as_ref().unwrap().previous.is_none()), "Another buffer starts over");
+}
+
+#[nvim_oxi::test]
+fn test_render_days_separates_sections() {
+    use time::{Date, Month};
+    use time_tracking_nvim::{aggregate::render_days, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h");
+    create_test_file(temp_dir.path(), "2024-01-02.md", "# 2024-01-02\n- review 30m");
+    let days = || (1..=2).map(|day| Date::from_calendar_date(2024, Month::January, day).unwrap());
+
+    let rendered = render_days(config_static, days());
+    assert_eq!(rendered.matches("\n\n---\n\n").count(), 1, "Days should be split by a horizontal rule");
+    assert!(rendered.find("## Monday 2024-01-01").unwrap() < rendered.find("---").unwrap());
+
+    options::set(Options { section_separator: String::new(), ..Options::default() });
+    let rendered = render_days(config_static, days());
+    options::set(Options::default());
+    assert!(!rendered.contains("---"), "An empty separator disables it");
+    assert!(rendered.contains("\n\n## Tuesday 2024-01-02"));
+}
+
+#[nvim_oxi::test]
+fn test_pinned_today_drives_week_totals() {
+    use nvim_oxi::{Dictionary, Object, conversion::FromObject};
+    use time_tracking_nvim::{aggregate::week_totals_fn, dates::{pin_today, today}, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- review 30m");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+
+    let wednesday = time::Date::from_calendar_date(2024, time::Month::January, 3).unwrap();
+    pin_today(Some(wednesday));
+    assert_eq!(today(), wednesday);
+
+    let totals = week_totals_fn(config_static);
+    pin_today(None);
+    assert_ne!(today(), wednesday, "Unpinning goes back to the real clock");
+
+    let days: Vec<(String, i64)> = totals
+        .into_iter()
+        .map(|day| {
+            let day = Dictionary::from_object(day).unwrap();
+            let field = |key: &str| -> Object {
+                day.iter().find(|(k, _)| k.to_string_lossy() == key).unwrap().1.clone()
+            };
+            (
+                String::from_object(field("date")).unwrap(),
+                i64::from_object(field("total_minutes")).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(days.len(), 7);
+    assert_eq!(days[0].0, "2023-12-30");
+    assert_eq!(days[4], ("2024-01-03".to_owned(), 30));
+}
+
+#[nvim_oxi::test]
+fn test_save_total_message() {
+    use time_tracking_nvim::{dates::pin_today, options::{self, Options}, save_total_message};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    assert_eq!(save_total_message(config_static).unwrap(), None, "Nothing outside tracking files");
+
+    let md_file = create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- coding 6h\n- review 30m");
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&md_file).unwrap();
+    buf.set_lines(.., false, ["# 2024-01-03", "- coding 6h", "- review 30m"]).unwrap();
+    api::set_current_buf(&buf).unwrap();
+
+    pin_today(time::Date::from_calendar_date(2024, time::Month::January, 3).ok());
+    let today_message = save_total_message(config_static).unwrap();
+    pin_today(time::Date::from_calendar_date(2024, time::Month::January, 4).ok());
+    let later_message = save_total_message(config_static).unwrap();
+    pin_today(None);
+
+    assert_eq!(today_message.as_deref(), Some("Today: 6h30m"));
+    assert_eq!(later_message.as_deref(), Some("Total: 6h30m"));
+}
+
+#[nvim_oxi::test]
+fn test_export_on_exit_writes_preview_summary() {
+    use time::{Date, Month};
+    use time_tracking_nvim::{dates::pin_today, export::{exit_export_path, export_on_exit_fn}, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    let root = fs::canonicalize(temp_dir.path()).unwrap();
+    let date = Date::from_calendar_date(2024, Month::January, 2).unwrap();
+
+    let template = "archive/%Y-%m-%d-summary.md";
+    assert_eq!(exit_export_path(config_static, template, date), Some(root.join("archive/2024-01-02-summary.md")));
+    assert_eq!(exit_export_path(config_static, "/tmp/%Y.md", date), Some("/tmp/2024.md".into()));
+
+    pin_today(Some(date));
+    options::set(Options { export_on_exit: Some(template.to_string()), ..Options::default() });
+    export_on_exit_fn(config_static).unwrap();
+    assert!(!root.join("archive").exists(), "Nothing is written without a preview");
+
+    create_or_update_preview("# Summary\n- coding 1h").unwrap();
+    export_on_exit_fn(config_static).unwrap();
+    options::set(Options::default());
+    pin_today(None);
+
+    let written = fs::read_to_string(root.join("archive/2024-01-02-summary.md")).unwrap();
+    assert_eq!(written, "# Summary\n- coding 1h\n");
+}
+
+#[nvim_oxi::test]
+fn test_render_csv_quotes_fields() {
+    use time_tracking_nvim::{dates::days_between, export::render_csv, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding, mostly 1h\n- review 30m");
+    create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- say \"hi\" 15m");
+
+    let start = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+    let end = time::Date::from_calendar_date(2024, time::Month::January, 3).unwrap();
+    assert_eq!(
+        render_csv(&config, days_between(start, end)),
+        "date,description,minutes\n\
+         2024-01-01,\"coding, mostly\",60\n\
+         2024-01-01,review,30\n\
+         2024-01-03,\"say \"\"hi\"\"\",15\n"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_export_entries_tags_and_json() {
+    use time_tracking_nvim::{dates::days_between, export::{ExportEntry, export_entries, render_json}, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options {
+        billable_marker: Some("$".to_string()),
+        ..Options::default()
+    });
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- [acme] coding $ 1h\n- lunch 30m");
+
+    let day = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+    let entries = export_entries(&config, days_between(day, day));
+    let json = render_json(&config, days_between(day, day), false);
+    options::set(Options::default());
+
+    assert_eq!(
+        entries[0],
+        ExportEntry {
+            date: "2024-01-01".to_string(),
+            description: "[acme] coding $".to_string(),
+            minutes: 60,
+            project: Some("acme".to_string()),
+            billable: Some(true),
+        }
+    );
+    assert_eq!(entries[1].project, None);
+    assert_eq!(entries[1].billable, Some(false));
+    assert_eq!(
+        json,
+        r#"[{"date":"2024-01-01","description":"[acme] coding $","minutes":60,"project":"acme","billable":true},{"date":"2024-01-01","description":"lunch","minutes":30,"billable":false}]"#
+    );
+}
+
+#[nvim_oxi::test]
+fn test_budget_warning_tracks_over_budget_days() {
+    use time_tracking_nvim::{options::{self, Options}, render_preview_content, state};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    let md_file = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 8h\n- email 45m");
+    api::command(&format!("edit {}", md_file.display())).unwrap();
+    let source = api::get_current_buf().handle();
+
+    options::set(Options {
+        daily_budget_minutes: 8 * 60,
+        ..Options::default()
+    });
+    render_preview_content(config_static).unwrap();
+    let over = state::with(|s| (s.budget_line.is_some(), s.budget_notified.contains(&source)));
+
+    options::set(Options {
+        daily_budget_minutes: 9 * 60,
+        ..Options::default()
+    });
+    render_preview_content(config_static).unwrap();
+    let under = state::with(|s| (s.budget_line.is_some(), s.budget_notified.contains(&source)));
+    options::set(Options::default());
+
+    assert_eq!(over, (true, true), "Over budget, the total line is flagged");
+    assert_eq!(under, (false, false), "Back under budget, the warning resets");
+}
+
+#[nvim_oxi::test]
+fn test_buffer_total_minutes() {
+    use time_tracking_nvim::{buffer_total_minutes, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    assert_eq!(buffer_total_minutes(config_static).unwrap(), None, "No total outside tracking files");
+
+    let md_file = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h30m\n- email 45m");
+    api::command(&format!("edit {}", md_file.display())).unwrap();
+    assert_eq!(buffer_total_minutes(config_static).unwrap(), Some(135));
+}
+
+#[nvim_oxi::test]
+fn test_status_indicator() {
+    use time_tracking_nvim::status_indicator;
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    assert_eq!(status_indicator(&config).unwrap(), "", "Empty outside tracking files");
+
+    let md_file = create_test_file(temp_dir.path(), "test.md", "# Test Content");
+    let mut buf = api::create_buf(false, false).unwrap();
+    buf.set_name(&md_file).unwrap();
+    api::set_current_buf(&buf).unwrap();
+    assert_eq!(status_indicator(&config).unwrap(), "○TT");
+
+    create_or_update_preview("# Test Content").unwrap();
+    assert_eq!(status_indicator(&config).unwrap(), "●TT");
+}
+
+#[nvim_oxi::test]
+fn test_close_all_previews_across_tabpages() {
+    use time_tracking_nvim::{close_all_previews, close_preview};
+
+    create_or_update_preview("# Test Content").unwrap();
+    api::command("tabnew").unwrap();
+    create_or_update_preview("# Test Content").unwrap();
+    assert_eq!(find_preview_windows().unwrap().len(), 2);
+
+    close_preview().unwrap();
+    assert_eq!(find_preview_windows().unwrap().len(), 1, "Close should only affect the current tabpage");
+
+    close_all_previews().unwrap();
+    assert!(find_preview_windows().unwrap().is_empty(), "CloseAll should close previews in every tabpage");
+}
+
+#[nvim_oxi::test]
+fn test_tab_scoped_previews_are_separate() {
+    use time_tracking_nvim::options::{self, Options, PreviewScope};
+
+    options::set(Options {
+        preview_scope: PreviewScope::Tab,
+        ..Options::default()
+    });
+
+    create_or_update_preview("# First Tab").unwrap();
+    let first = find_preview_buffer().unwrap().expect("First tab should have a preview");
+    api::command("tabnew").unwrap();
+    assert!(find_preview_buffer().unwrap().is_none(), "A new tab starts without a preview");
+
+    create_or_update_preview("# Second Tab").unwrap();
+    let second = find_preview_buffer().unwrap().expect("Second tab should have a preview");
+    options::set(Options::default());
+
+    assert_ne!(first.handle(), second.handle());
+    let first_line = first.get_lines(0..1, false).unwrap().next().unwrap();
+    assert_eq!(first_line.to_string_lossy(), "# First Tab", "Other tabs' previews are untouched");
+    assert_eq!(find_preview_windows().unwrap().len(), 2);
+}
+
+#[nvim_oxi::test]
+fn test_window_scoped_previews_get_their_own_windows() {
+    use time_tracking_nvim::options::{self, Options, PreviewScope};
+
+    options::set(Options {
+        preview_scope: PreviewScope::Window,
+        ..Options::default()
+    });
+
+    let first_source = api::get_current_win();
+    create_or_update_preview("# First Window").unwrap();
+    let first = find_preview_window().unwrap().expect("First window should have a preview");
+
+    api::set_current_win(&first_source).unwrap();
+    api::command("split").unwrap();
+    assert!(find_preview_window().unwrap().is_none(), "Another source window has no preview yet");
+    create_or_update_preview("# Second Window").unwrap();
+    let second = find_preview_window().unwrap().expect("Second window should have a preview");
+    options::set(Options::default());
+
+    assert_ne!(first.handle(), second.handle(), "Each source window gets its own preview window");
+    let first_line = first.get_buf().unwrap().get_lines(0..1, false).unwrap().next().unwrap();
+    assert_eq!(first_line.to_string_lossy(), "# First Window", "The first preview keeps its buffer");
+    assert_eq!(find_preview_windows().unwrap().len(), 2);
+}
+
+#[nvim_oxi::test]
+fn test_preview_scrollbind_set_and_cleared() {
+    use time_tracking_nvim::{close_preview, options::{self, Options}};
+
+    options::set(Options {
+        preview_scrollbind: true,
+        ..Options::default()
+    });
+
+    let source = api::get_current_win();
+    create_or_update_preview("# Test Content").unwrap();
+    let preview = find_preview_windows().unwrap().pop().expect("Preview window should be open");
+
+    let scrollbind = |win: &nvim_oxi::api::Window| -> bool {
+        let wopts = nvim_oxi::api::opts::OptionOptsBuilder::default().win(win.clone()).build();
+        api::get_option_value("scrollbind", &wopts).unwrap()
+    };
+    assert!(scrollbind(&source), "Source window should be scroll-bound");
+    assert!(scrollbind(&preview), "Preview window should be scroll-bound");
+
+    close_preview().unwrap();
+    assert!(!scrollbind(&source), "Closing the preview should clear scrollbind on the source");
+}
+
+#[nvim_oxi::test]
+fn test_preview_dim_sets_winhighlight_on_preview_only() {
+    use time_tracking_nvim::{PREVIEW_DIM_HIGHLIGHT, options::{self, Options}};
+
+    options::set(Options {
+        preview_dim: true,
+        ..Options::default()
+    });
+
+    let source = api::get_current_win();
+    create_or_update_preview("# Test Content").unwrap();
+    let preview = find_preview_windows().unwrap().pop().expect("Preview window should be open");
+    options::set(Options::default());
+
+    let winhighlight = |win: &nvim_oxi::api::Window| -> String {
+        let wopts = nvim_oxi::api::opts::OptionOptsBuilder::default().win(win.clone()).build();
+        api::get_option_value("winhighlight", &wopts).unwrap()
+    };
+    assert_eq!(winhighlight(&preview), PREVIEW_DIM_HIGHLIGHT);
+    assert_eq!(winhighlight(&source), "", "The source window should not be dimmed");
+}
+
+#[nvim_oxi::test]
+fn test_preview_max_lines_truncates_with_marker() {
+    use time_tracking_nvim::options::{self, Options};
+
+    options::set(Options {
+        preview_max_lines: 3,
+        ..Options::default()
+    });
+    create_or_update_preview("# Range\n- a\n- b\n- c\n- d").unwrap();
+    let buf = find_preview_buffer().unwrap().expect("Preview buffer should exist");
+    options::set(Options::default());
+
+    let lines: Vec<String> = buf.get_lines(.., false).unwrap()
+        .map(|s| s.to_string_lossy().into())
+        .collect();
+    assert_eq!(lines, vec!["# Range", "- a", "- b", "… (2 more lines)"]);
+
+    create_or_update_preview("# Range\n- a\n- b\n- c\n- d").unwrap();
+    assert_eq!(buf.line_count().unwrap(), 5, "Unlimited by default");
+}
+
+#[nvim_oxi::test]
+fn test_edit_command_creates_file_and_opens_preview() {
+    use time_tracking_nvim::time_tracking_with_config;
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+
+    // Start from a buffer that isn't a tracking file
+    api::command("enew").unwrap();
+    api::command("TimeTrackingEdit 2024-01-02").unwrap();
+
+    let path = fs::canonicalize(temp_dir.path()).unwrap().join("2024-01-02.md");
+    assert!(path.exists(), "The day's file should be created");
+    let current = fs::canonicalize(api::get_current_buf().get_name().unwrap()).unwrap();
+    assert_eq!(current, path, "The day's file should be the current buffer");
+    assert!(find_preview_window().unwrap().is_some(), "Its preview should be open");
+}
+
+#[nvim_oxi::test]
+fn test_run_cli_reloads_rewritten_file() {
+    use time_tracking_nvim::{options::{self, Options}, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-02.md", "# 2024-01-02\n- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    options::set(Options {
+        cli_command: Some("printf '%s\\n' '- review 30m' >>".to_string()),
+        ..Options::default()
+    });
+    api::command("TimeTrackingRunCli").unwrap();
+
+    let lines: Vec<String> = api::get_current_buf().get_lines(.., false).unwrap()
+        .map(|s| s.to_string_lossy().into())
+        .collect();
+    assert_eq!(lines, vec!["# 2024-01-02", "- coding 1h", "- review 30m"], "The buffer should be reloaded");
+
+    options::set(Options {
+        cli_command: Some("false".to_string()),
+        ..Options::default()
+    });
+    api::command("TimeTrackingRunCli").unwrap();
+    options::set(Options::default());
+    assert_eq!(api::get_current_buf().line_count().unwrap(), 3, "A failed command leaves the file alone");
+}
+
+#[nvim_oxi::test]
+fn test_annotation_refresh_keeps_source_view() {
+    use nvim_oxi::{Array, Dictionary};
+    use time_tracking_nvim::{state, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let content: String = (0..200).map(|i| format!("- task {} 15m\n", i)).collect();
+    let path = create_test_file(temp_dir.path(), "2024-01-02.md", &format!("# 2024-01-02\n{}", content));
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+    api::command("150 | normal! zt5l").unwrap();
+
+    let view = || api::call_function::<_, Dictionary>("winsaveview", Array::new()).unwrap();
+    let before = view();
+    state::with(|s| {
+        s.inline = true;
+        s.signs = true;
+    });
+    api::command("TimeTrackingUpdateInline").unwrap();
+    api::command("TimeTrackingUpdateSigns").unwrap();
+    state::with(|s| {
+        s.inline = false;
+        s.signs = false;
+    });
+
+    assert_eq!(view(), before, "Refreshing annotations should leave the source view alone");
+}
+
+#[nvim_oxi::test]
+fn test_preview_wrap_sets_window_options() {
+    use nvim_oxi::api::opts::OptionOptsBuilder;
+    use time_tracking_nvim::{close_preview, options::{self, Options}};
+
+    let window_option = |name: &str| -> bool {
+        let win = find_preview_window().unwrap().expect("Preview window should be open");
+        api::get_option_value(name, &OptionOptsBuilder::default().win(win).build()).unwrap()
+    };
+
+    create_or_update_preview("# Test Content\n- a rather long description that won't fit 1h").unwrap();
+    assert!(window_option("wrap"), "Wrapping is on by default");
+    assert!(window_option("linebreak"));
+    assert!(window_option("breakindent"));
+
+    close_preview().unwrap();
+    options::set(Options { preview_wrap: false, ..Options::default() });
+    create_or_update_preview("# Test Content").unwrap();
+    options::set(Options::default());
+    assert!(!window_option("wrap"), "preview_wrap = false should truncate long lines");
+}
+
+#[nvim_oxi::test]
+fn test_copy_entry_copies_duration() {
+    use nvim_oxi::Object;
+    use time_tracking_nvim::copy_entry_fn;
+
+    create_or_update_preview("# 2024-01-01\n\n- coding: 1h30m").unwrap();
+    let preview = find_preview_window().unwrap().expect("Preview window should be open");
+    api::set_current_win(&preview).unwrap();
+
+    api::get_current_win().set_cursor(3, 0).unwrap();
+    copy_entry_fn().unwrap();
+    // Headless Neovim may have no clipboard to read back from
+    if api::call_function::<_, i64>("has", ("clipboard",)).unwrap() == 1 {
+        let copied: String = api::call_function("getreg", ("+",)).unwrap();
+        assert_eq!(copied, "1h30m");
+    }
+
+    // A heading isn't an entry: that's left to the normal `y`
+    api::get_current_win().set_cursor(1, 0).unwrap();
+    copy_entry_fn().unwrap();
+    let _: Object = api::call_function("feedkeys", ("\x1b", "nx")).unwrap();
+}
+
+#[nvim_oxi::test]
+fn test_auto_open_with_multiple_files_previews_visible_one() {
+    use time_tracking_nvim::{auto_open_preview_impl, state, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let first = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    let second = create_test_file(temp_dir.path(), "2024-01-02.md", "# 2024-01-02\n- review 2h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+
+    api::command(&format!("args {} {}", first.display(), second.display())).unwrap();
+    api::command("TimeTrackingAutoOpen").unwrap();
+    let visible = api::get_current_buf();
+
+    // The second file is in the argument list but not displayed
+    let hidden = api::list_bufs()
+        .find(|buf| buf.get_name().unwrap().ends_with("2024-01-02.md"))
+        .expect("The second argument should have a buffer");
+    hidden.call(move |_| auto_open_preview_impl(config_static).unwrap()).unwrap();
+
+    assert_eq!(find_preview_windows().unwrap().len(), 1, "At most one preview should open");
+    assert_eq!(
+        state::with(|s| s.rendered_from),
+        Some(visible.handle()),
+        "The preview should be for the visible file"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_reset_command_restores_fresh_state() {
+    use time_tracking_nvim::{state, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+    api::command("TimeTrackingToggle").unwrap();
+    assert!(find_preview_window().unwrap().is_some(), "Preview should be open");
+
+    let generation = state::with(|s| {
+        s.pinned = true;
+        s.paused = true;
+        s.preview_width = Some(42);
+        s.render_generation
+    });
+    api::command("TimeTrackingReset").unwrap();
+
+    assert!(find_preview_windows().unwrap().is_empty(), "Every preview should be closed");
+    state::with(|s| {
+        assert!(!s.pinned && !s.paused, "Pin and pause flags should be cleared");
+        assert_eq!(s.preview_width, None, "Remembered widths should be forgotten");
+        assert!(s.task_snapshots.is_none() && s.closed_preview.is_none());
+        assert!(s.render_generation > generation, "Pending debounced updates should be cancelled");
+    });
+}
+
+#[nvim_oxi::test]
+fn test_reset_reports_once_per_session_warnings_again() {
+    use time_tracking_nvim::{entries::parse_entries, options::{self, Options}, time_tracking_with_config};
+
+    let (config, _temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = { parse_hook = function() error("boom") end }"#).unwrap();
+    api::command("lua _G.tt_notify, _G.tt_warnings = vim.notify, 0").unwrap();
+    api::command("lua vim.notify = function() _G.tt_warnings = _G.tt_warnings + 1 end").unwrap();
+    options::set(Options { parse_hook: true, ..Options::default() });
+
+    parse_entries("- coding 1h");
+    parse_entries("- coding 1h");
+    let before: i64 = api::call_function("luaeval", ("_G.tt_warnings",)).unwrap();
+    api::command("TimeTrackingReset").unwrap();
+    parse_entries("- coding 1h");
+    let after: i64 = api::call_function("luaeval", ("_G.tt_warnings",)).unwrap();
+
+    options::set(Options::default());
+    api::command("lua vim.notify = _G.tt_notify").unwrap();
+    api::command(r#"lua package.loaded["time-tracking-nvim"] = nil"#).unwrap();
+
+    assert_eq!(before, 1, "A failing hook is reported once");
+    assert_eq!(after, 2, "After a reset it's reported again");
+}
+
+#[nvim_oxi::test]
+fn test_export_rejects_reversed_ranges_and_creates_directories() {
+    use time_tracking_nvim::time_tracking_with_config;
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+
+    // The rejection is reported as an error, which may surface here
+    let reversed = temp_dir.path().join("reversed.csv");
+    let _ = api::command(&format!(
+        "TimeTrackingExportCsv 2024-01-05 2024-01-01 {}",
+        reversed.display()
+    ));
+    assert!(!reversed.exists(), "A reversed range writes nothing");
+
+    let nested = temp_dir.path().join("exports").join("week.json");
+    api::command(&format!(
+        "TimeTrackingExportJson 2024-01-01 2024-01-05 {}",
+        nested.display()
+    ))
+    .unwrap();
+    assert!(nested.exists(), "Missing directories are created");
+}
+
+#[nvim_oxi::test]
+fn test_insert_header_accepts_relative_dates() {
+    use time_tracking_nvim::{dates::pin_today, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-02.md", "- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    pin_today(Some(time::Date::from_calendar_date(2024, time::Month::January, 2).unwrap()));
+    api::command("TimeTrackingInsertHeader yesterday").unwrap();
+    pin_today(None);
+
+    let first = api::get_current_buf().get_lines(0..1, false).unwrap().next().unwrap();
+    assert_eq!(first.to_string_lossy(), "# 2024-01-01");
+}
+
+#[nvim_oxi::test]
+fn test_preview_layout_sets_width() {
+    use nvim_oxi::{Dictionary, Object};
+    use time_tracking_nvim::options::{self, Options, PreviewLayout};
+
+    assert_eq!(PreviewLayout::default().width(120), 40);
+    assert_eq!(PreviewLayout::Half.width(120), 60);
+    assert_eq!(PreviewLayout::TwoThirdsRight.width(120), 80);
+    assert_eq!(PreviewLayout::ThirdRight.width(30), 20, "Never narrower than 20 columns");
+
+    let parsed = Options::from_dict(&Dictionary::from_iter([("preview_layout", Object::from("two-thirds-right"))]));
+    assert_eq!(parsed.preview_layout, PreviewLayout::TwoThirdsRight);
+
+    api::set_option_value("columns", 120, &Default::default()).unwrap();
+    options::set(Options { preview_layout: PreviewLayout::TwoThirdsRight, ..Options::default() });
+    create_or_update_preview("# Test Content").unwrap();
+    options::set(Options::default());
+
+    let preview = find_preview_window().unwrap().expect("Preview window should be open");
+    assert_eq!(preview.get_width().unwrap(), 80, "The preview should take two thirds of the screen");
+}
+
+#[nvim_oxi::test]
+fn test_inline_mode_never_opens_preview() {
+    use nvim_oxi::{Dictionary, Object};
+    use time_tracking_nvim::options::{self, DisplayMode, Options};
+
+    let parsed = Options::from_dict(&Dictionary::from_iter([("mode", Object::from("inline"))]));
+    assert_eq!(parsed.mode, DisplayMode::Inline);
+    let unknown = Options::from_dict(&Dictionary::from_iter([("mode", Object::from("sidebar"))]));
+    assert_eq!(unknown.mode, DisplayMode::Preview, "Unknown modes fall back to the preview");
+
+    options::set(Options { mode: DisplayMode::Inline, ..Options::default() });
+    create_or_update_preview("# Test Content").unwrap();
+    options::set(Options::default());
+
+    assert!(find_preview_window().unwrap().is_none(), "Inline mode should never open a preview");
+    assert!(find_preview_buffer().unwrap().is_none(), "Nor create its buffer");
+}
+
+#[nvim_oxi::test]
+fn test_reload_switches_display_mode() {
+    use time_tracking_nvim::{options::{self, Options}, state, time_tracking_with_config};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+    api::command("TimeTrackingToggle").unwrap();
+    assert!(find_preview_window().unwrap().is_some(), "Preview should be open");
+
+    api::command(r#"lua vim.g.time_tracking_nvim = { mode = "inline" }"#).unwrap();
+    api::command("TimeTrackingReload").unwrap();
+    assert!(find_preview_windows().unwrap().is_empty(), "Switching to inline closes the previews");
+    assert!(state::with(|s| s.inline), "Inline annotations should be on");
+    api::command("TimeTrackingToggle").unwrap();
+    assert!(!state::with(|s| s.inline), "The toggle should follow the reloaded mode");
+    assert!(find_preview_window().unwrap().is_none(), "Nor open a preview");
+
+    api::command("lua vim.g.time_tracking_nvim = nil").unwrap();
+    api::command("TimeTrackingReload").unwrap();
+    api::command("TimeTrackingToggle").unwrap();
+    options::set(Options::default());
+    assert!(find_preview_window().unwrap().is_some(), "Back in preview mode the toggle opens the preview");
+}
+
+#[nvim_oxi::test]
+fn test_range_view_survives_live_updates() {
+    use time_tracking_nvim::{state::{self, View}, time_tracking_with_config, update_preview_fn};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    api::command("TimeTrackingRange 2024-01-01 2024-01-02").unwrap();
+    update_preview_fn(config_static).unwrap();
+    let preview = find_preview_buffer().unwrap().expect("The range should open the preview");
+    let first_line = preview.get_lines(0..1, false).unwrap().next().unwrap();
+    let view = state::with(|s| std::mem::take(&mut s.view));
+
+    assert_eq!(first_line.to_string_lossy(), "# 2024-01-01 – 2024-01-02", "A live update should re-render the range");
+    assert!(matches!(view, View::Range(..)));
+}
+
+#[nvim_oxi::test]
+fn test_ytd_view_survives_live_updates() {
+    use time_tracking_nvim::{state::{self, View}, time_tracking_with_config, update_preview_fn};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    api::command("TimeTrackingYTD").unwrap();
+    update_preview_fn(config_static).unwrap();
+    let preview = find_preview_buffer().unwrap().expect("The report should open the preview");
+    let first_line = preview.get_lines(0..1, false).unwrap().next().unwrap();
+    let view = state::with(|s| std::mem::take(&mut s.view));
+
+    assert!(first_line.to_string_lossy().ends_with("(year to date)"), "A live update should re-render the report");
+    assert_eq!(view, View::Ytd);
+}
+
+#[nvim_oxi::test]
+fn test_compare_view_survives_live_updates() {
+    use time_tracking_nvim::{state::{self, View}, time_tracking_with_config, update_preview_fn};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h\n");
+    create_test_file(temp_dir.path(), "2024-01-02.md", "# 2024-01-02\n- coding 2h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    api::command("TimeTrackingCompare 2024-01-01 2024-01-02").unwrap();
+    update_preview_fn(config_static).unwrap();
+    let preview = find_preview_buffer().unwrap().expect("The comparison should open the preview");
+    let first_line = preview.get_lines(0..1, false).unwrap().next().unwrap();
+    let view = state::with(|s| std::mem::take(&mut s.view));
+
+    assert_eq!(first_line.to_string_lossy(), "# 2024-01-01 vs 2024-01-02", "A live update should re-render the comparison");
+    assert!(matches!(view, View::Compare(..)));
+}
+
+#[nvim_oxi::test]
+fn test_projects_view_survives_live_updates() {
+    use time_tracking_nvim::{state::{self, View}, time_tracking_with_config, update_preview_fn};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let path = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- [ProjX] coding 1h\n");
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    time_tracking_with_config(config_static).unwrap();
+    api::command(&format!("edit {}", path.display())).unwrap();
+
+    api::command("TimeTrackingProjects").unwrap();
+    let mut buf = api::get_current_buf();
+    buf.set_lines(2..2, false, ["- [ProjY] review 30m"]).unwrap();
+    update_preview_fn(config_static).unwrap();
+    let preview = find_preview_buffer().unwrap().expect("The report should open the preview");
+    let lines: Vec<String> = preview.get_lines(0..preview.line_count().unwrap(), false).unwrap()
+        .map(|line| line.to_string_lossy().into_owned()).collect();
+    let view = state::with(|s| std::mem::take(&mut s.view));
+
+    assert!(lines.contains(&"- ProjY: 30m".to_string()), "A live update should re-render the report: {lines:?}");
+    assert_eq!(view, View::Projects(None));
+}
+
+#[nvim_oxi::test]
+fn test_is_window_busy_recognizes_layout_errors() {
+    use time_tracking_nvim::is_window_busy;
+
+    assert!(is_window_busy(&"E242: Can't split a window while closing another"));
+    assert!(is_window_busy(&"Vim:E1159: Cannot split a window when closing the buffer"));
+    assert!(is_window_busy(&"E1312: Not allowed to change the window layout in this autocmd"));
+    assert!(!is_window_busy(&"E36: Not enough room"), "Other failures should still be reported");
+}
+
+#[nvim_oxi::test]
+fn test_focus_preview_on_open() {
+    use time_tracking_nvim::{options::{self, Options}, toggle_preview_fn};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    let config_static: &'static Config = Box::leak(Box::new(config));
+    let md_file = create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h");
+    api::command(&format!("edit {}", md_file.display())).unwrap();
+    let source = api::get_current_win();
+
+    options::set(Options {
+        focus_preview_on_open: true,
+        ..Options::default()
+    });
+    toggle_preview_fn(config_static).unwrap();
+    options::set(Options::default());
+
+    let preview = find_preview_window().unwrap().expect("Preview window should be open");
+    assert_eq!(api::get_current_win(), preview, "The cursor should stay in the preview");
+    assert_ne!(preview, source);
+}
+
+#[nvim_oxi::test]
+fn test_undo_close_restores_snapshot() {
+    use time_tracking_nvim::{close_preview, options::{self, Options}, undo_close_fn};
+
+    options::set(Options::default());
+    create_or_update_preview("# Snapshot\n- coding: 1h").unwrap();
+    close_preview().unwrap();
+    assert!(find_preview_window().unwrap().is_none());
+
+    undo_close_fn().unwrap();
+    let preview = find_preview_buffer().unwrap().expect("Preview should be reopened");
+    let lines: Vec<String> = preview
+        .get_lines(0..preview.line_count().unwrap(), false)
+        .unwrap()
+        .map(|line| line.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(lines, vec!["# Snapshot", "- coding: 1h"]);
+    assert!(find_preview_window().unwrap().is_some());
+}
+
+#[nvim_oxi::test]
+fn test_zoom_preview_and_restore() {
+    use time_tracking_nvim::{options::{self, Options}, zoom_preview_fn};
+
+    options::set(Options::default());
+    zoom_preview_fn().unwrap();
+    assert!(find_preview_window().unwrap().is_none(), "Zoom without a preview is a no-op");
+
+    let source = api::get_current_win();
+    create_or_update_preview("# Test Content").unwrap();
+    let preview = find_preview_window().unwrap().expect("Preview window should be open");
+    let before = (source.get_width().unwrap(), preview.get_width().unwrap());
+
+    zoom_preview_fn().unwrap();
+    assert!(preview.get_width().unwrap() > before.1, "Zoom should widen the preview");
+    assert!(source.get_width().unwrap() < before.0, "Zoom should shrink the source");
+
+    zoom_preview_fn().unwrap();
+    assert_eq!((source.get_width().unwrap(), preview.get_width().unwrap()), before);
+}
+
+#[nvim_oxi::test]
+fn test_preview_hardened_with_noedit() {
+    use time_tracking_nvim::state;
+
+    state::with(|s| s.noedit = true);
+    create_or_update_preview("# Test Content").unwrap();
+
+    let preview_buffer = find_preview_buffer().unwrap().expect("Preview buffer should exist");
+    let bopts = nvim_oxi::api::opts::OptionOptsBuilder::default()
+        .buffer(preview_buffer.clone())
+        .build();
+    let readonly: bool = api::get_option_value("readonly", &bopts).unwrap();
+    assert!(readonly, "Preview should be readonly with noedit");
+
+    let mapped = preview_buffer
+        .get_keymap(nvim_oxi::api::types::Mode::Normal)
+        .unwrap()
+        .any(|map| map.lhs == "i");
+    assert!(mapped, "Edit keys should be mapped to no-ops with noedit");
+
+    // Content updates still go through
+    create_or_update_preview("# Updated").unwrap();
+    let lines: Vec<String> = preview_buffer.get_lines(.., false).unwrap()
+        .map(|s| s.to_string_lossy().into())
+        .collect();
+    assert_eq!(lines.join("\n"), "# Updated");
+}
+
+#[nvim_oxi::test]
+fn test_expand_tilde() {
+    let home = std::env::var("HOME").expect("HOME should be set");
+    assert_eq!(expand_tilde("~"), std::path::PathBuf::from(&home));
+    assert_eq!(expand_tilde("~/notes"), std::path::Path::new(&home).join("notes"));
+    assert_eq!(expand_tilde("~other/notes"), std::path::PathBuf::from("~other/notes"));
+    assert_eq!(expand_tilde("/tmp/notes"), std::path::PathBuf::from("/tmp/notes"));
+}
+
+#[nvim_oxi::test]
+fn test_parse_entries_decimal_hours_mixed_with_units() {
+    use time_tracking_nvim::{entries::{parse_entries, total_minutes}, options::{self, Options}};
+
+    let content = "# 2024-01-01\n- Design 1h30m\n- Review 0.5\n- Email 45m";
+
+    options::set(Options::default());
+    assert_eq!(total_minutes(&parse_entries(content)), 135, "Plain numbers are ignored by default");
+
+    options::set(Options {
+        decimal_hours_pattern: Some(r"\s(\d+(?:\.\d+)?)$".to_string()),
+        ..Options::default()
+    });
+    let entries = parse_entries(content);
+    options::set(Options::default());
+
+    assert_eq!(entries[1].description, "Review");
+    assert_eq!(entries[1].minutes, 30);
+    assert_eq!(total_minutes(&entries), 165);
+}
+
+#[nvim_oxi::test]
+fn test_parse_entries_range_spanning_midnight() {
+    use time_tracking_nvim::entries::{format_duration, parse_entries};
+
+    let entries = parse_entries("- late deploy 23:30-00:45");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].minutes, 75);
+    assert_eq!(format_duration(entries[0].minutes), "1h15m");
+}
+
+#[nvim_oxi::test]
+fn test_preview_width_kept_after_manual_resize() {
+    use time_tracking_nvim::{close_preview, options::{self, Options}};
+
+    options::set(Options {
+        preview_remember_width: true,
+        ..Options::default()
+    });
+
+    create_or_update_preview("# Test Content").unwrap();
+    let mut preview = find_preview_windows().unwrap().pop().expect("Preview window should be open");
+    preview.set_width(33).unwrap();
+
+    // Updating an open preview must not reset the width
+    create_or_update_preview("# Updated").unwrap();
+    assert_eq!(preview.get_width().unwrap(), 33);
+
+    // Reopening uses the remembered width
+    close_preview().unwrap();
+    create_or_update_preview("# Reopened").unwrap();
+    let reopened = find_preview_windows().unwrap().pop().expect("Preview window should be open");
+    assert_eq!(reopened.get_width().unwrap(), 33);
+}
+
+#[nvim_oxi::test]
+fn test_map_entry_lines() {
+    use time_tracking_nvim::entries::{map_entry_lines, parse_entries};
+
+    let entries = parse_entries("# 2024-01-01\n\n- coding 2h\n- review 30m");
+    let output = "Summary\n- coding: 2h\n- review: 30m\nTotal: 2h30m";
+    let mapping = map_entry_lines(&entries, output);
+
+    assert_eq!(mapping.get(&1), Some(&2));
+    assert_eq!(mapping.get(&2), Some(&3));
+    assert_eq!(mapping.get(&0), None, "Headings map to nothing");
+    assert_eq!(mapping.get(&3), None, "Totals map to nothing");
+}
+
+#[nvim_oxi::test]
+fn test_sort_entry_lines_within_sections() {
+    use time_tracking_nvim::entries::sort_entry_lines;
+
+    let lines: Vec<String> = [
+        "# 2024-01-01",
+        "- email 15m",
+        "- coding 2h",
+        "notes stay here",
+        "- review 30m",
+        "## Afternoon",
+        "- standup 15m",
+        "- design 1h",
+    ]
+    .iter()
+    .map(|line| line.to_string())
+    .collect();
+
+    assert_eq!(
+        sort_entry_lines(&lines, false),
+        vec![
+            "# 2024-01-01",
+            "- coding 2h",
+            "- review 30m",
+            "notes stay here",
+            "- email 15m",
+            "## Afternoon",
+            "- design 1h",
+            "- standup 15m",
+        ]
+    );
+
+    let ascending = sort_entry_lines(&lines, true);
+    assert_eq!(ascending[1..5], ["- email 15m", "- review 30m", "notes stay here", "- coding 2h"]);
+    assert_eq!(ascending[6..], ["- standup 15m", "- design 1h"]);
+}
+
+#[nvim_oxi::test]
+fn test_preview_name_is_configurable() {
+    use time_tracking_nvim::options::{self, Options};
+
+    options::set(Options {
+        preview_name: "[Zeiterfassung]".to_string(),
+        ..Options::default()
+    });
+    create_or_update_preview("# Test Content").unwrap();
+
+    let preview_buffer = find_preview_buffer().unwrap().expect("Preview should be found by its marker");
+    assert!(preview_buffer.get_name().unwrap().ends_with("[Zeiterfassung]"));
+
+    // A user buffer that merely has the default name isn't mistaken for it
+    let mut decoy = api::create_buf(false, false).unwrap();
+    decoy.set_name("[Time Tracking Preview]").unwrap();
+    assert!(!is_preview_buffer(&decoy).unwrap());
+}
+
+#[nvim_oxi::test]
+fn test_render_range_totals() {
+    use time_tracking_nvim::{aggregate::render_range, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h");
+    create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- review 30m");
+
+    let start = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+    let end = time::Date::from_calendar_date(2024, time::Month::January, 14).unwrap();
+    assert_eq!(
+        render_range(&config, start, end),
+        "# 2024-01-01 – 2024-01-14\n\n- Monday 2024-01-01: 1h\n- Wednesday 2024-01-03: 30m\n\nTotal: 1h30m"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_render_range_with_relative_dates() {
+    use std::collections::HashMap;
+    use time_tracking_nvim::{aggregate::render_range, dates::pin_today, options::{self, Options}, state};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options {
+        i18n: HashMap::from([("yesterday".to_string(), "Gestern".to_string())]),
+        ..Options::default()
+    });
+    create_test_file(temp_dir.path(), "2024-01-01.md", "# 2024-01-01\n- coding 1h");
+    create_test_file(temp_dir.path(), "2024-01-03.md", "# 2024-01-03\n- review 30m");
+    create_test_file(temp_dir.path(), "2024-01-04.md", "# 2024-01-04\n- email 15m");
+
+    let start = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+    let end = time::Date::from_calendar_date(2024, time::Month::January, 4).unwrap();
+    pin_today(Some(end));
+    state::with(|s| s.relative_dates = true);
+    let output = render_range(&config, start, end);
+    state::with(|s| s.relative_dates = false);
+    pin_today(None);
+    options::set(Options::default());
+
+    assert_eq!(
+        output,
+        "# 2024-01-01 – 2024-01-04\n\n- Monday 2024-01-01: 1h\n- Gestern: 30m\n- Today: 15m\n\nTotal: 1h45m"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_render_ytd_by_month() {
+    use time_tracking_nvim::{aggregate::render_ytd, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-05.md", "# 2024-01-05\n- coding 1h");
+    create_test_file(temp_dir.path(), "2024-03-02.md", "# 2024-03-02\n- review 30m");
+
+    let today = time::Date::from_calendar_date(2024, time::Month::March, 10).unwrap();
+    assert_eq!(
+        render_ytd(&config, today),
+        "# 2024 (year to date)\n\n- January: 1h\n- February: 0m\n- March: 30m\n\nTotal: 1h30m"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_render_range_includes_gzipped_archives() {
+    use flate2::{Compression, write::GzEncoder};
+    use time_tracking_nvim::{aggregate::render_range, options::{self, Options}};
+
+    let (config, temp_dir) = create_test_config_with_temp_dir();
+    options::set(Options::default());
+    create_test_file(temp_dir.path(), "2024-01-02.md", "# 2024-01-02\n- coding 1h");
+
+    let archive = File::create(temp_dir.path().join("2024-01-01.md.gz")).unwrap();
+    let mut encoder = GzEncoder::new(archive, Compression::default());
+    encoder.write_all(b"# 2024-01-01\n- planning 2h").unwrap();
+    encoder.finish().unwrap();
+
+    let start = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+    let end = time::Date::from_calendar_date(2024, time::Month::January, 2).unwrap();
+    assert_eq!(
+        render_range(&config, start, end),
+        "# 2024-01-01 – 2024-01-02\n\n- Monday 2024-01-01: 2h\n- Tuesday 2024-01-02: 1h\n\nTotal: 3h"
+    );
+}
+
+#[nvim_oxi::test]
+fn test_with_footer_total_position() {
+    use time_tracking_nvim::{options::{self, Options, TotalPosition}, with_footer};
+
+    let body = "# 2024-01-01\n\n- coding: 1h";
+
+    options::set(Options::default());
+    assert_eq!(with_footer(body, "Total: 1h"), "# 2024-01-01\n\n- coding: 1h\n\nTotal: 1h");
+
+    options::set(Options {
+        total_position: TotalPosition::Top,
+        ..Options::default()
+    });
+    assert_eq!(with_footer(body, "Total: 1h"), "# 2024-01-01\n\nTotal: 1h\n\n- coding: 1h");
+    assert_eq!(with_footer("- coding: 1h", "Total: 1h"), "Total: 1h\n\n- coding: 1h");
+}
@@ -14,6 +14,56 @@ local default_config = {
 	-- preview_width = nil, -- Will use 1/3 of screen width
 	auto_download = true, -- Automatically download binaries if missing
 	auto_update = true, -- Automatically update binary when plugin version changes
+	header_format = "# %Y-%m-%d", -- Header inserted by :TimeTrackingInsertHeader
+	-- preview_filetype = "markdown", -- Filetype for the preview buffer
+	preview_conceal = false, -- Conceal markup in the preview (markdown-like filetypes only)
+	pin_preview = false, -- Start with the preview pinned so it isn't auto-closed
+	i18n = {}, -- Translations for plugin-generated labels, e.g. { total = "Gesamt" }
+	-- number_locale = "en", -- Group digits in plugin-computed numbers (1,234)
+	daily_file_pattern = "%Y-%m-%d.md", -- Daily file location relative to the data directory
+	rescan_interval = 2000, -- Milliseconds to reuse the data directory listing before walking it again
+	preview_min_total_columns = 80, -- Skip the preview split on narrower terminals
+	group_by_tag = false, -- List the day's entries under their tags with per-tag subtotals
+	tag_pattern = "#[\\w-]+", -- Tag regex for group_by_tag (first capture group or whole match)
+	project_tag_pattern = "\\[([^\\]]+)\\]", -- Project tag regex for :TimeTrackingProjects (first capture group is the name)
+	-- ignore_prefix = "//", -- Lines starting with this are notes, never counted as entries
+	-- billable_marker = "$", -- Show billable/non-billable subtotals for entries containing this marker
+	signs = false, -- Mark time entry lines in the sign column (toggle with :TimeTrackingSigns)
+	sign_text = "▎", -- Sign text for entry lines
+	sign_hl = "DiagnosticSignOk", -- Highlight group for entry signs
+	preview_scrollbind = false, -- Scroll the preview in sync with the source window
+	content_join = "\n", -- Line separator used when passing the buffer to the formatter
+	preview_remember_width = false, -- Reopen the preview at its last width instead of 1/3 of the screen
+	goto_entry_key = "<CR>", -- Preview key that jumps to the entry's source line ("" disables)
+	copy_entry_key = "y", -- Preview key that copies the entry's duration to the + register ("" disables)
+	preview_name = "[Time Tracking Preview]", -- Display name of the preview buffer
+	auto_create_today = false, -- On startup in the data directory, create and open today's file
+	total_position = "bottom", -- Put computed totals at the "top" or "bottom" of the preview
+	low_power = false, -- Update only on InsertLeave/BufWritePost and use longer auto-open/close delays
+	-- update_events = { "CursorHold", "CursorHoldI" }, -- Events that refresh the preview, instead of the preset ones
+	-- preview_open_command = "topleft 40vsplit", -- Open the preview with this command instead of the default split
+	notify_on_save = false, -- Notify the running total whenever a tracking file is saved
+	-- export_on_exit = "archive/%Y-%m-%d-summary.md", -- Write the preview's summary here on exit
+	formatters = {}, -- Formatter per extension, e.g. { md = "Default", csv = "Csv" }
+	preview_dim = false, -- Dim the preview window (winhighlight Normal:NormalNC)
+	update_debounce = 50, -- Milliseconds to coalesce edits/buffer switches before re-rendering (0 = immediate)
+	-- decimal_hours_pattern = [[\s(\d+(?:\.\d+)?)$]], -- Count trailing plain hours like "Design 2.5"
+	preview_layout = "third-right", -- Preview width: "third-right", "half" or "two-thirds-right"
+	preview_scope = "global", -- "global", "tab" (one preview per tabpage) or "window" (one per source window)
+	focus_preview_on_open = false, -- Keep the cursor in the preview after opening it explicitly
+	daily_budget_minutes = 0, -- Highlight the total past this many minutes a day (0 = off)
+	budget_notify = false, -- Also notify once when going over the daily budget
+	budget_hl = "DiagnosticWarn", -- Highlight group for an over-budget total
+	relative_dates = false, -- "Today"/"Yesterday" in plugin-rendered headings
+	trim_content = false, -- Strip leading/trailing blank lines before formatting
+	preview_max_lines = 0, -- Truncate the preview past this many lines (0 = unlimited)
+	section_separator = "---", -- Separator between days in aggregated views ("" disables)
+	mode = "preview", -- "preview" window or "inline" durations plus a winbar total
+	-- cli_command = "time-tracking-cli --reformat {file}", -- Command :TimeTrackingRunCli runs on the current file
+	preview_wrap = true, -- Wrap long preview lines at word boundaries instead of truncating them
+	warn_overlaps = false, -- Warn in the preview about overlapping time ranges
+	show_seconds = false, -- Show seconds ("1h30m15s") in plugin-computed durations
+	-- parse_hook = function(content) return { { description = "coding", minutes = 90 } } end, -- Replaces the built-in entry parser
 }
 
 -- Add the binary directory to Lua's cpath
@@ -298,6 +348,15 @@ function M.setup(opts)
 	-- Store config for other functions
 	M.config = config
 
+	-- Functions can't go through vim.g, so the native module is only told
+	-- there's a parse_hook and calls it through M.parse_hook
+	M.parse_hook = type(config.parse_hook) == "function" and config.parse_hook or nil
+	local native_config = vim.deepcopy(config)
+	native_config.parse_hook = M.parse_hook ~= nil
+
+	-- Expose the options to the native module, which reads them on load
+	vim.g.time_tracking_nvim = native_config
+
 	-- Get binary path
 	local binary_path, target = get_binary_path()
 	if not binary_path then
@@ -524,6 +583,75 @@ function M.close()
 	vim.cmd("TimeTrackingClose")
 end
 
+-- Daily totals for the current week as a list of { date, total_minutes }
+-- (days without a file report 0), e.g. for a sparkline
+function M.week_totals()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return {}
+	end
+	return native.week_totals()
+end
+
+-- Every entry of the current week as a list of { date, description, minutes,
+-- project?, billable? }, the same records TimeTrackingExportJson writes
+function M.week_entries()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return {}
+	end
+	return native.week_entries()
+end
+
+-- Total minutes of the current buffer's entries as an integer, or nil outside
+-- tracking files, for your own formatting and thresholds
+function M.today_total_minutes()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return nil
+	end
+	return native.today_total_minutes()
+end
+
+-- Absolute paths of every tracking file under data_directory, sorted, e.g. to
+-- feed a Telescope or fzf picker
+function M.tracking_files()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return {}
+	end
+	return native.tracking_files()
+end
+
+-- Short indicator for a statusline: "●TT" with the preview open, "○TT" in a
+-- tracking file without it, "" otherwise
+function M.status()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return ""
+	end
+	return native.status()
+end
+
+-- Whether the current buffer is a time tracking file, using the plugin's own
+-- detection; false if the native module isn't available
+function M.is_tracking_file()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return false
+	end
+	return native.is_tracking_file()
+end
+
+-- Window id of the preview in the current tabpage, or nil if it isn't open
+function M.preview_win()
+	local ok, native = pcall(require, "time_tracking_nvim")
+	if not ok then
+		return nil
+	end
+	return native.preview_win()
+end
+
 -- Manual download function for troubleshooting
 function M.download()
 	local binary_path, target = get_binary_path()
@@ -0,0 +1,432 @@
+use super::*;
+
+use nvim_oxi::Array;
+
+use time::{Date, Weekday};
+
+use crate::dates::{
+    days_between, format_date, parse_weekday, relative_day, resolve_date, today, week_range,
+};
+use crate::entries::{
+    Entry, format_delta, format_precise, parse_day, parse_entries, total_seconds,
+};
+use crate::labels::label;
+use crate::state::{TaskSeconds, TaskSnapshots, View};
+
+/// Format `date` for a plugin-generated heading: "Today", "Yesterday" or
+/// "Tomorrow" while relative dates are on and it's that close, otherwise
+/// per `format`
+pub fn heading_date(date: Date, format: &str) -> String {
+    if state::with(|s| s.relative_dates) {
+        if let Some(key) = relative_day(date, today()) {
+            return label(key);
+        }
+    }
+    format_date(date, format)
+}
+
+/// Turn relative dates in plugin-generated headings on or off
+pub fn toggle_relative_dates_fn(config: &'static Config) -> Result<()> {
+    state::with(|s| s.relative_dates = !s.relative_dates);
+    update_preview_fn(config)
+}
+
+/// Read the daily file for `date` (or its gzipped archive), if there is one
+pub fn load_day(config: &Config, date: Date) -> Option<String> {
+    files::read_daily_file(config, date)
+}
+
+/// The configured first day of the week, defaulting to Monday when it's
+/// unset or doesn't name a weekday
+pub fn week_start_day(config: &Config) -> Weekday {
+    config
+        .week_start_day
+        .as_deref()
+        .and_then(parse_weekday)
+        .unwrap_or(Weekday::Monday)
+}
+
+/// Warn at setup when `week_start_day` doesn't name a weekday, rather than
+/// silently computing weeks from Monday
+pub fn validate_week_start_day(config: &Config) {
+    if let Some(value) = config.week_start_day.as_deref() {
+        if parse_weekday(value).is_none() {
+            log_warn!(
+                "[time-tracking] invalid week_start_day '{}', using Monday",
+                value
+            );
+        }
+    }
+}
+
+/// Total seconds for each of `dates`, `None` for days without a file
+pub fn day_totals(
+    config: &Config,
+    dates: impl IntoIterator<Item = Date>,
+) -> Vec<(Date, Option<i64>)> {
+    dates
+        .into_iter()
+        .map(|day| {
+            let seconds =
+                load_day(config, day).map(|content| total_seconds(&parse_entries(&content)));
+            (day, seconds)
+        })
+        .collect()
+}
+
+/// Total minutes for each day of the week containing `date`, per
+/// `week_start_day`; days without a file count as zero
+pub fn week_totals(config: &Config, date: Date) -> Vec<(Date, i64)> {
+    let (start, end) = week_range(date, week_start_day(config), 0);
+    day_totals(config, days_between(start, end))
+        .into_iter()
+        .map(|(day, seconds)| (day, seconds.unwrap_or(0) / 60))
+        .collect()
+}
+
+/// [`week_totals`] for the current week as a list of
+/// `{ date = "YYYY-MM-DD", total_minutes = n }` tables, for Lua callers
+pub fn week_totals_fn(config: &'static Config) -> Array {
+    week_totals(config, today())
+        .into_iter()
+        .map(|(date, minutes)| {
+            Dictionary::from_iter([
+                ("date", Object::from(format_date(date, "%Y-%m-%d"))),
+                ("total_minutes", Object::from(minutes)),
+            ])
+        })
+        .collect()
+}
+
+/// Render each date's summary as its own section, skipping dates with no file.
+/// Sections are split by the `section_separator` option.
+pub fn render_days(config: &'static Config, dates: impl IntoIterator<Item = Date>) -> String {
+    let mut sections = Vec::new();
+    for date in dates {
+        if let Some(content) = load_day(config, date) {
+            let heading = format!("## {}", heading_date(date, "%A %Y-%m-%d"));
+            sections.push(format!(
+                "{}\n{}",
+                heading,
+                render_day_summary(config, &content, &parse_day(&content))
+            ));
+        }
+    }
+    let separator = &options::get().section_separator;
+    if separator.is_empty() {
+        sections.join("\n\n")
+    } else {
+        sections.join(&format!("\n\n{}\n\n", separator))
+    }
+}
+
+/// Render the week `offset` weeks from the current one (0 = this week, -1 =
+/// last), returning the content and a label describing the resolved range
+pub fn render_week(config: &'static Config, offset: i64) -> (String, String) {
+    let (start, end) = week_range(today(), week_start_day(config), offset);
+    let range = format!(
+        "{} {} – {}",
+        label("week"),
+        format_date(start, "%Y-%m-%d"),
+        format_date(end, "%Y-%m-%d")
+    );
+
+    let output = format!(
+        "# {}\n\n{}",
+        range,
+        render_days(config, days_between(start, end))
+    );
+    (output, range)
+}
+
+/// Show the week `offset` weeks from the current one in the preview, switching
+/// it to week view so live updates keep showing that week
+pub fn week_fn(config: &'static Config, offset: i64) -> Result<()> {
+    state::with(|s| {
+        s.view = View::Week;
+        s.week_offset = offset;
+    });
+
+    let (output, range) = render_week(config, offset);
+    create_or_update_preview(&output)?;
+
+    // Echo the resolved range so it's clear which week is shown
+    api::echo([(range.as_str(), None::<&str>)], false, &Default::default())?;
+
+    Ok(())
+}
+
+/// Week offset from a `TimeTrackingWeek` invocation: an explicit signed
+/// argument wins, otherwise a count means "that many weeks ago"
+pub fn week_offset(args: &CommandArgs) -> std::result::Result<i64, String> {
+    if let Some(arg) = args
+        .args
+        .as_deref()
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+    {
+        return arg
+            .parse::<i64>()
+            .map_err(|_| format!("invalid week offset '{}', expected e.g. 0 or -1", arg));
+    }
+
+    Ok(match args.count {
+        Some(count) if count > 0 => -(count as i64),
+        _ => 0,
+    })
+}
+
+/// Render the total for each day with a file in `start..=end`, plus the grand total
+pub fn render_range(config: &Config, start: Date, end: Date) -> String {
+    let mut out = format!(
+        "# {} – {}\n",
+        format_date(start, "%Y-%m-%d"),
+        format_date(end, "%Y-%m-%d")
+    );
+
+    let days: Vec<(Date, i64)> = day_totals(config, days_between(start, end))
+        .into_iter()
+        .filter_map(|(day, seconds)| Some((day, seconds?)))
+        .collect();
+    if days.is_empty() {
+        out.push_str(&format!("\n_{}_", label("no_data")));
+        return out;
+    }
+
+    for (day, seconds) in &days {
+        out.push_str(&format!(
+            "\n- {}: {}",
+            heading_date(*day, "%A %Y-%m-%d"),
+            format_precise(*seconds)
+        ));
+    }
+    let total: i64 = days.iter().map(|(_, seconds)| seconds).sum();
+    with_footer(
+        &out,
+        &format!("{}: {}", label("total"), format_precise(total)),
+    )
+}
+
+/// Show per-day totals and the grand total for an inclusive date range,
+/// switching the preview to that view so live updates keep showing it
+pub fn range_fn(config: &'static Config, args: &[String]) -> Result<()> {
+    let [start, end] = args else {
+        log_error!("[time-tracking] TimeTrackingRange expects a start and an end date");
+        return Ok(());
+    };
+
+    let today = today();
+    let (Some(start), Some(end)) = (resolve_date(start, today), resolve_date(end, today)) else {
+        log_error!("[time-tracking] invalid date in '{} {}'", start, end);
+        return Ok(());
+    };
+    if start > end {
+        log_error!(
+            "[time-tracking] range start {} is after its end {}",
+            format_date(start, "%Y-%m-%d"),
+            format_date(end, "%Y-%m-%d")
+        );
+        return Ok(());
+    }
+
+    state::with(|s| s.view = View::Range(start, end));
+    create_or_update_preview(&render_range(config, start, end))
+}
+
+/// Render a total for each month from January 1 of `date`'s year through
+/// `date`, plus the grand total. Months without files count as zero.
+pub fn render_ytd(config: &Config, date: Date) -> String {
+    let start = date.replace_ordinal(1).unwrap_or(date);
+
+    let mut months: Vec<(Date, i64)> = Vec::new();
+    for (day, seconds) in day_totals(config, days_between(start, date)) {
+        match months.last_mut() {
+            Some((month, total)) if month.month() == day.month() => *total += seconds.unwrap_or(0),
+            _ => months.push((day, seconds.unwrap_or(0))),
+        }
+    }
+
+    let mut out = format!("# {} ({})\n", date.year(), label("ytd"));
+    for (month, seconds) in &months {
+        out.push_str(&format!(
+            "\n- {}: {}",
+            format_date(*month, "%B"),
+            format_precise(*seconds)
+        ));
+    }
+    let total: i64 = months.iter().map(|(_, seconds)| seconds).sum();
+    with_footer(
+        &out,
+        &format!("{}: {}", label("total"), format_precise(total)),
+    )
+}
+
+/// Show this year's month-by-month totals in the preview, switching it to
+/// that view so live updates keep showing it
+pub fn ytd_fn(config: &'static Config) -> Result<()> {
+    state::with(|s| s.view = View::Ytd);
+    create_or_update_preview(&render_ytd(config, today()))
+}
+
+/// Compare two days in the preview, see [`render_compare`], switching it to
+/// that view so live updates keep showing it
+pub fn compare_fn(config: &'static Config, args: &[String]) -> Result<()> {
+    let [first, second] = args else {
+        log_error!("[time-tracking] TimeTrackingCompare expects two dates");
+        return Ok(());
+    };
+
+    let today = today();
+    let (Some(first), Some(second)) = (resolve_date(first, today), resolve_date(second, today))
+    else {
+        log_error!("[time-tracking] invalid date in '{} {}'", first, second);
+        return Ok(());
+    };
+
+    state::with(|s| s.view = View::Compare(first, second));
+    create_or_update_preview(&render_compare(config, first, second))
+}
+
+/// Render two days' summaries one after the other, followed by the change in
+/// time for each task that appears on both days
+pub fn render_compare(config: &Config, first: Date, second: Date) -> String {
+    let first_content = load_day(config, first);
+    let second_content = load_day(config, second);
+    let first_day = first_content.as_deref().map(parse_day);
+    let second_day = second_content.as_deref().map(parse_day);
+
+    let mut sections = vec![format!(
+        "# {} vs {}",
+        format_date(first, "%Y-%m-%d"),
+        format_date(second, "%Y-%m-%d")
+    )];
+    for (date, content, day) in [
+        (first, &first_content, &first_day),
+        (second, &second_content, &second_day),
+    ] {
+        let body = match (content, day) {
+            (Some(content), Some(day)) => render_day_summary(config, content, day),
+            _ => format!("_{}_", label("no_data")),
+        };
+        sections.push(format!(
+            "## {}\n{}",
+            heading_date(date, "%A %Y-%m-%d"),
+            body
+        ));
+    }
+
+    if let (Some(first_day), Some(second_day)) = (&first_day, &second_day) {
+        let before = seconds_by_task(&first_day.entries);
+        let after = seconds_by_task(&second_day.entries);
+        let deltas: Vec<String> = before
+            .iter()
+            .filter_map(|(task, (name, old))| {
+                let (_, new) = after.get(task)?;
+                Some(format!(
+                    "- {}: {} → {} ({})",
+                    name,
+                    format_precise(*old),
+                    format_precise(*new),
+                    format_delta(new - old)
+                ))
+            })
+            .collect();
+
+        if !deltas.is_empty() {
+            sections.push(format!("## {}\n{}", label("delta"), deltas.join("\n")));
+        }
+    }
+
+    sections.join("\n\n")
+}
+
+/// Total seconds per task, keyed case-insensitively, keeping the first spelling
+pub fn seconds_by_task(entries: &[Entry]) -> TaskSeconds {
+    let mut tasks = TaskSeconds::new();
+    for entry in entries {
+        tasks
+            .entry(entry.description.to_lowercase())
+            .or_insert_with(|| (entry.description.clone(), 0))
+            .1 += entry.duration_seconds();
+    }
+    tasks
+}
+
+/// Remember `source`'s task durations after a refresh. Only refreshes that
+/// change them count, so the previous snapshot is the state before the most
+/// recent change.
+pub fn record_task_snapshot(source: i32, tasks: TaskSeconds) {
+    state::with(|s| match &mut s.task_snapshots {
+        Some(snapshots) if snapshots.source == source => {
+            if snapshots.latest != tasks {
+                snapshots.previous = Some(std::mem::replace(&mut snapshots.latest, tasks));
+            }
+        }
+        _ => {
+            s.task_snapshots = Some(TaskSnapshots {
+                source,
+                previous: None,
+                latest: tasks,
+            })
+        }
+    });
+}
+
+/// Describe what changed between two task snapshots: added (`+`), changed
+/// (`~`) and removed (`-`) tasks, then the net difference
+pub fn render_task_delta(before: &TaskSeconds, after: &TaskSeconds) -> String {
+    let mut lines = Vec::new();
+    for (task, (name, new)) in after {
+        match before.get(task) {
+            None => lines.push(format!("+ {}: {}", name, format_precise(*new))),
+            Some((_, old)) if old != new => lines.push(format!(
+                "~ {}: {} → {} ({})",
+                name,
+                format_precise(*old),
+                format_precise(*new),
+                format_delta(new - old)
+            )),
+            Some(_) => {}
+        }
+    }
+    for (task, (name, old)) in before {
+        if !after.contains_key(task) {
+            lines.push(format!("- {}: {}", name, format_precise(*old)));
+        }
+    }
+
+    let total = |tasks: &TaskSeconds| tasks.values().map(|(_, seconds)| seconds).sum::<i64>();
+    lines.push(format!(
+        "{}: {}",
+        label("delta"),
+        format_delta(total(after) - total(before))
+    ));
+    lines.join("\n")
+}
+
+/// Notify what changed in the current buffer's tasks at the last preview
+/// refresh that changed them
+pub fn delta_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let current = api::get_current_buf().handle();
+    let snapshots = state::with(|s| {
+        s.task_snapshots
+            .as_ref()
+            .filter(|snapshots| snapshots.source == current)
+            .and_then(|snapshots| Some((snapshots.previous.clone()?, snapshots.latest.clone())))
+    });
+    let Some((before, after)) = snapshots else {
+        log_warn!("[time-tracking] no earlier update of this file to compare with");
+        return Ok(());
+    };
+
+    api::notify(
+        &render_task_delta(&before, &after),
+        LogLevel::Info,
+        &Default::default(),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use super::*;
+
+use crate::aggregate::load_day;
+use crate::dates::today;
+use crate::entries::carry_over_lines;
+use crate::projects::current_buffer_date;
+use crate::utils::buffer_lines;
+
+/// Append the previous day's entries to the current tracking buffer, skipping
+/// lines it already has; with `zero`, each carried entry starts at `0m`.
+///
+/// The previous day is relative to the buffer's date when its path matches
+/// the `daily_file_pattern`, otherwise to today.
+pub fn carry_over_fn(config: &'static Config, arg: Option<&str>) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        log_warn!("[time-tracking] TimeTrackingCarryOver works in a tracking file");
+        return Ok(());
+    }
+
+    let zero = match arg.map(str::trim).unwrap_or("") {
+        "" => false,
+        "zero" => true,
+        other => {
+            log_error!(
+                "[time-tracking] invalid argument '{}', expected zero or nothing",
+                other
+            );
+            return Ok(());
+        }
+    };
+
+    let date = current_buffer_date(config).unwrap_or_else(today);
+    let Some(previous) = date.previous_day().and_then(|day| load_day(config, day)) else {
+        log_warn!("[time-tracking] no file for the previous day to carry over");
+        return Ok(());
+    };
+
+    let mut buf = api::get_current_buf();
+    let lines = buffer_lines(&buf)?;
+    let carried = carry_over_lines(&previous, &lines, zero);
+    if carried.is_empty() {
+        return Ok(());
+    }
+
+    // After the last non-blank line, so trailing blank lines stay trailing
+    let end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    buf.set_lines(end..end, false, carried.iter().map(String::as_str))?;
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+use std::process::Command;
+
+/// Placeholder in `cli_command` for the current file's path
+const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Save the current tracking file, run `cli_command` on it and wait for it,
+/// then reload the buffer from disk and refresh the preview
+pub fn run_cli_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+    let Some(template) = options::get().cli_command.clone() else {
+        log_error!("[time-tracking] set `cli_command` to use TimeTrackingRunCli");
+        return Ok(());
+    };
+
+    // The command works on the file, so it has to see unsaved edits, and
+    // reloading afterwards would drop them
+    api::command("silent update")?;
+
+    let path = api::get_current_buf().get_name()?;
+    let quoted: String = api::call_function("shellescape", (path.to_string_lossy().into_owned(),))?;
+    let command_line = cli_command_line(&template, &quoted);
+
+    let output = match shell_command(&command_line)?.output() {
+        Ok(output) => output,
+        Err(e) => {
+            log_error!("[time-tracking] failed to run `{}`: {}", command_line, e);
+            return Ok(());
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_owned(),
+        };
+        log_error!("[time-tracking] `{}` failed: {}", command_line, detail);
+        return Ok(());
+    }
+
+    api::command("silent edit!")?;
+    update_preview_fn(config)?;
+    update_inline_fn(config)
+}
+
+/// Expand `{file}` in a `cli_command` to the (already shell-quoted) path,
+/// or append the path if the command doesn't mention it
+pub fn cli_command_line(template: &str, quoted_path: &str) -> String {
+    if template.contains(FILE_PLACEHOLDER) {
+        template.replace(FILE_PLACEHOLDER, quoted_path)
+    } else {
+        format!("{} {}", template, quoted_path)
+    }
+}
+
+/// `command_line` run through Vim's 'shell' and 'shellcmdflag', as `:!` would
+fn shell_command(command_line: &str) -> Result<Command> {
+    let opts = Default::default();
+    let shell: String = api::get_option_value("shell", &opts)?;
+    let flag: String = api::get_option_value("shellcmdflag", &opts)?;
+
+    let mut command = Command::new(shell);
+    command.args(flag.split_whitespace()).arg(command_line);
+    Ok(command)
+}
@@ -0,0 +1,138 @@
+use std::cell::Cell;
+
+use time::{Date, Duration, Month, OffsetDateTime, Weekday};
+
+/// Relative tokens accepted wherever a date argument is
+pub const RELATIVE_DATE_TOKENS: &[&str] = &["today", "yesterday", "tomorrow", "-1", "-7"];
+
+thread_local! {
+    static PINNED_TODAY: Cell<Option<Date>> = const { Cell::new(None) };
+}
+
+/// Today's date in local time, falling back to UTC if the offset is unknown.
+///
+/// Returns the pinned date instead while one is set (see [`pin_today`]).
+pub fn today() -> Date {
+    if let Some(date) = PINNED_TODAY.with(Cell::get) {
+        return date;
+    }
+    OffsetDateTime::now_local()
+        .unwrap_or_else(|_| OffsetDateTime::now_utc())
+        .date()
+}
+
+/// Make [`today`] return `date` instead of reading the clock, or go back to
+/// the real clock with `None`. Meant for tests of date-relative features.
+pub fn pin_today(date: Option<Date>) {
+    PINNED_TODAY.with(|pinned| pinned.set(date));
+}
+
+/// Label key (`today`, `yesterday` or `tomorrow`) for `date` relative to
+/// `today`, if it's that close
+pub fn relative_day(date: Date, today: Date) -> Option<&'static str> {
+    match (date - today).whole_days() {
+        0 => Some("today"),
+        -1 => Some("yesterday"),
+        1 => Some("tomorrow"),
+        _ => None,
+    }
+}
+
+/// Parse an ISO `YYYY-MM-DD` date
+pub fn parse_iso_date(input: &str) -> Option<Date> {
+    let mut parts = input.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+
+    Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()
+}
+
+/// Resolve a date argument relative to `today`.
+///
+/// Accepts `today`, `yesterday`, `tomorrow`, signed day offsets like `-1` or
+/// `+3`, and ISO `YYYY-MM-DD` dates.
+pub fn resolve_date(input: &str, today: Date) -> Option<Date> {
+    let input = input.trim();
+    match input.to_ascii_lowercase().as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.previous_day(),
+        "tomorrow" => return today.next_day(),
+        _ => {}
+    }
+
+    if input.starts_with(['-', '+']) {
+        let offset = input.parse::<i64>().ok()?;
+        return today.checked_add(Duration::days(offset));
+    }
+
+    parse_iso_date(input)
+}
+
+/// Expand a strftime-style format for `date`.
+///
+/// Supports `%Y`, `%m`, `%d`, `%e`, `%j`, `%A`, `%a`, `%B`, `%b` and `%%`;
+/// anything else is copied through unchanged.
+pub fn format_date(date: Date, format: &str) -> String {
+    let mut out = String::with_capacity(format.len() + 8);
+    let mut chars = format.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", date.year())),
+            Some('m') => out.push_str(&format!("{:02}", u8::from(date.month()))),
+            Some('d') => out.push_str(&format!("{:02}", date.day())),
+            Some('e') => out.push_str(&format!("{:>2}", date.day())),
+            Some('j') => out.push_str(&format!("{:03}", date.ordinal())),
+            Some('A') => out.push_str(&date.weekday().to_string()),
+            Some('a') => out.push_str(&date.weekday().to_string()[..3]),
+            Some('B') => out.push_str(&date.month().to_string()),
+            Some('b') => out.push_str(&date.month().to_string()[..3]),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+
+    out
+}
+
+/// Parse a weekday name like `Saturday` or `sat` (case-insensitive)
+pub fn parse_weekday(input: &str) -> Option<Weekday> {
+    let input = input.trim().to_ascii_lowercase();
+    let weekdays = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    weekdays.into_iter().find(|day| {
+        let name = day.to_string().to_ascii_lowercase();
+        input.len() >= 3 && name.starts_with(&input)
+    })
+}
+
+/// First and last day of the week containing `date`, shifted by `offset` weeks
+pub fn week_range(date: Date, week_start: Weekday, offset: i64) -> (Date, Date) {
+    let days_into_week = date.weekday().number_days_from_monday() as i64
+        - week_start.number_days_from_monday() as i64;
+    let start = date - Duration::days(days_into_week.rem_euclid(7)) + Duration::weeks(offset);
+    (start, start + Duration::days(6))
+}
+
+/// Every date from `start` to `end`, inclusive
+pub fn days_between(start: Date, end: Date) -> impl Iterator<Item = Date> {
+    std::iter::successors(Some(start), |day| day.next_day()).take_while(move |day| *day <= end)
+}
@@ -0,0 +1,120 @@
+use super::*;
+
+use crate::dates::parse_weekday;
+use crate::hook::clear_hook_warning;
+use crate::utils::clear_non_utf8_warnings;
+
+/// Echo the config fields that drive detection and formatting, with paths
+/// resolved the same way detection resolves them. Read-only.
+pub fn show_config_fn(config: &'static Config) -> Result<()> {
+    let data_directory = match config.get_data_directory() {
+        Some(dir) => match files::data_dir(config) {
+            Some(resolved) => resolved.display().to_string(),
+            None => format!("{} (does not exist)", dir),
+        },
+        None => "(unset)".to_owned(),
+    };
+    let (prefix, suffix) = state::with(|s| (s.prefix.clone(), s.suffix.clone()));
+
+    let fields = [
+        ("data_directory", data_directory),
+        ("week_start_day", describe_week_start(config)),
+        ("formatter", format!("{:?}", config.formatter)),
+        (
+            "prefix",
+            describe_affix(config.get_prefix(), prefix.as_deref()),
+        ),
+        (
+            "suffix",
+            describe_affix(config.get_suffix(), suffix.as_deref()),
+        ),
+    ];
+
+    let mut chunks: Vec<(String, Option<&str>)> =
+        vec![("time-tracking-nvim config:".to_owned(), Some("Title"))];
+    for (name, value) in fields {
+        chunks.push((format!("\n  {}: ", name), None));
+        chunks.push((value, Some("String")));
+    }
+
+    api::echo(chunks, true, &Default::default())
+}
+
+/// Echo the formatters the CLI supports, marking the one the current buffer
+/// is formatted with (its `formatters` mapping, else the config's)
+pub fn list_formatters_fn(config: &'static Config) -> Result<()> {
+    let names = formatter_names();
+    if names.is_empty() {
+        log_warn!("[time-tracking] the CLI doesn't expose its formatter names");
+        return Ok(());
+    }
+
+    let name = api::get_current_buf().get_name()?;
+    let extension = name.extension().map(|ext| ext.to_string_lossy());
+    let active = config_for_extension(config, extension.as_deref())
+        .formatter
+        .as_ref()
+        .map(|formatter| format!("{:?}", formatter));
+
+    let mut chunks: Vec<(String, Option<&str>)> =
+        vec![("time-tracking-nvim formatters:".to_owned(), Some("Title"))];
+    for name in names {
+        if active.as_deref() == Some(*name) {
+            chunks.push((format!("\n* {}", name), Some("String")));
+        } else {
+            chunks.push((format!("\n  {}", name), None));
+        }
+    }
+    if active.is_none() {
+        chunks.push((
+            "\n(formatter unset, the CLI's default applies)".to_owned(),
+            Some("Comment"),
+        ));
+    }
+
+    api::echo(chunks, true, &Default::default())
+}
+
+/// Close every preview, drop the caches and runtime state and start over as
+/// if the plugin had just been set up, keeping the current options
+pub fn reset_fn(config: &'static Config) -> Result<()> {
+    close_all_previews()?;
+    clear_all_signs()?;
+    clear_all_inline()?;
+    clear_detection_cache();
+    files::invalidate_listing();
+    // Warnings shown once per session are due again
+    clear_non_utf8_warnings();
+    clear_hook_warning();
+
+    state::with(|s| {
+        // Pending debounced updates check the generation, so moving past it
+        // cancels them
+        let generation = s.render_generation;
+        *s = state::State::default();
+        s.render_generation = generation + 1;
+        init_state(config, s);
+    });
+
+    // Put back the annotations the options start with
+    update_signs_fn(config)?;
+    update_inline_fn(config)
+}
+
+/// Show `week_start_day` along with the weekday it resolved to
+fn describe_week_start(config: &Config) -> String {
+    let resolved = week_start_day(config);
+    match config.week_start_day.as_deref() {
+        Some(value) if parse_weekday(value).is_some() => format!("{} ({})", value, resolved),
+        Some(value) => format!("{} (invalid, using {})", value, resolved),
+        None => format!("(unset, using {})", resolved),
+    }
+}
+
+/// Show a config prefix/suffix, noting any runtime override in effect
+fn describe_affix(configured: &str, overridden: Option<&str>) -> String {
+    match overridden {
+        Some(active) => format!("{:?} (overridden, config: {:?})", active, configured),
+        None => format!("{:?}", configured),
+    }
+}
@@ -0,0 +1,444 @@
+use std::{cell::RefCell, collections::HashMap, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::hook::hook_entries;
+use crate::labels::format_number;
+use crate::options;
+use crate::state;
+
+/// `09:00-10:30` style time range anywhere in a line
+static RANGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(\d{1,2}):(\d{2})\s*-\s*(\d{1,2}):(\d{2})\b").expect("valid range regex")
+});
+
+/// Trailing duration such as `2h`, `1.5h`, `1h30m`, `45m` or `1h30m15s`
+static DURATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(?:^|[\s:,(\[-])(?:(\d+(?:\.\d+)?)\s*h)?\s*(?:(\d+)\s*m)?\s*(?:(\d+)\s*s)?\s*[)\]]?\s*$",
+    )
+    .expect("valid duration regex")
+});
+
+/// A line the plugin recognizes as a time entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Zero-based line number in the source content
+    pub line: usize,
+    /// The entry text with its duration/time range removed
+    pub description: String,
+    /// Duration in whole minutes
+    pub minutes: i64,
+    /// Seconds past `minutes`, from a trailing seconds part like `15s`
+    pub seconds: i64,
+}
+
+impl Entry {
+    /// The full duration in seconds
+    pub fn duration_seconds(&self) -> i64 {
+        self.minutes * 60 + self.seconds
+    }
+}
+
+/// A day's entries, parsed once and shared by everything a render computes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedDay {
+    /// The parsed content's lines, which the entries' line numbers index
+    pub lines: Vec<String>,
+    pub entries: Vec<Entry>,
+    /// Whether the `parse_hook` produced the entries, in which case the
+    /// preview is built from them instead of by the formatter
+    pub hooked: bool,
+}
+
+/// Parse the time entries out of a day's content.
+///
+/// This is the plugin's own lightweight parser, used for plugin-side totals
+/// and views; the preview text itself still comes from the formatter. A
+/// `parse_hook` set in `setup()` replaces it.
+pub fn parse_entries(content: &str) -> Vec<Entry> {
+    parse_day(content).entries
+}
+
+/// [`parse_entries`], noting whether the `parse_hook` was used. The hook is
+/// called back in Lua, so a render parses once and passes the result along.
+pub fn parse_day(content: &str) -> ParsedDay {
+    let lines: Vec<String> = content.lines().map(str::to_owned).collect();
+    let hooked = options::get().parse_hook.then(|| hook_entries(content));
+    if let Some(entries) = hooked.flatten() {
+        return ParsedDay {
+            lines,
+            entries,
+            hooked: true,
+        };
+    }
+
+    let entries = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, text)| parse_entry(line, text))
+        .collect();
+    ParsedDay {
+        lines,
+        entries,
+        hooked: false,
+    }
+}
+
+/// Parse a single line, returning `None` for headings, lines starting with
+/// the `ignore_prefix` and other non-entry lines
+pub fn parse_entry(line: usize, text: &str) -> Option<Entry> {
+    let text = text.trim();
+    if text.is_empty() || text.starts_with('#') || text.starts_with(SUBTOTAL) || is_ignored(text) {
+        return None;
+    }
+
+    let (minutes, seconds, description) = if let Some(caps) = RANGE.captures(text) {
+        let start = clock_minutes(&caps[1], &caps[2])?;
+        let end = clock_minutes(&caps[3], &caps[4])?;
+        let whole = caps.get(0)?;
+        let description = format!("{} {}", &text[..whole.start()], &text[whole.end()..]);
+        // An end before the start means the entry ran past midnight
+        let end = if end < start { end + 24 * 60 } else { end };
+        (end - start, 0, description)
+    } else {
+        let (seconds, start) = unit_duration(text)
+            .or_else(|| decimal_hours(text).map(|(minutes, start)| (minutes * 60, start)))?;
+        (seconds / 60, seconds % 60, text[..start].to_owned())
+    };
+
+    Some(Entry {
+        line,
+        description: clean_description(&description),
+        minutes,
+        seconds,
+    })
+}
+
+/// A time-range entry's span in minutes since midnight; ranges running past
+/// midnight end after `24:00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryRange {
+    /// Zero-based line number in the source content
+    pub line: usize,
+    pub start: i64,
+    pub end: i64,
+}
+
+/// The time-range entries of a day's content, in line order
+pub fn entry_ranges(content: &str) -> Vec<EntryRange> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(line, text)| parse_entry(*line, text).is_some())
+        .filter_map(|(line, text)| {
+            let caps = RANGE.captures(text.trim())?;
+            let start = clock_minutes(&caps[1], &caps[2])?;
+            let end = clock_minutes(&caps[3], &caps[4])?;
+            let end = if end < start { end + 24 * 60 } else { end };
+            Some(EntryRange { line, start, end })
+        })
+        .collect()
+}
+
+/// Pairs of time ranges in a day's content that overlap, in line order.
+/// Ranges that only touch (one ends as the next starts) don't overlap.
+pub fn overlapping_ranges(content: &str) -> Vec<(EntryRange, EntryRange)> {
+    let ranges = entry_ranges(content);
+    let mut overlaps = Vec::new();
+    for (i, a) in ranges.iter().enumerate() {
+        for b in &ranges[i + 1..] {
+            if a.start < b.end && b.start < a.end {
+                overlaps.push((*a, *b));
+            }
+        }
+    }
+    overlaps
+}
+
+/// `HH:MM` for minutes since midnight, wrapping past `24:00`
+pub fn format_clock(minutes: i64) -> String {
+    let minutes = minutes.rem_euclid(24 * 60);
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Whether `text` (already trimmed) starts with the `ignore_prefix` option
+fn is_ignored(text: &str) -> bool {
+    options::get()
+        .ignore_prefix
+        .as_deref()
+        .is_some_and(|prefix| text.starts_with(prefix.trim()))
+}
+
+/// Reorder the entry lines within each `#` section by duration, longest first
+/// unless `ascending`. Headings and non-entry lines keep their positions and
+/// entries with equal durations keep their relative order.
+pub fn sort_entry_lines(lines: &[String], ascending: bool) -> Vec<String> {
+    let mut sorted = lines.to_vec();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| line.trim_start().starts_with('#'))
+            .map_or(lines.len(), |i| start + 1 + i);
+
+        let mut entries: Vec<Entry> = (start..end)
+            .filter_map(|i| parse_entry(i, &lines[i]))
+            .collect();
+        let slots: Vec<usize> = entries.iter().map(|e| e.line).collect();
+        entries.sort_by_key(|e| if ascending { e.minutes } else { -e.minutes });
+        for (slot, entry) in slots.into_iter().zip(entries) {
+            sorted[slot] = lines[entry.line].clone();
+        }
+
+        start = end;
+    }
+    sorted
+}
+
+/// The entry lines of `previous` to add to a day that already has `existing`
+/// lines, in order and without any line that's already there (ignoring
+/// surrounding whitespace). With `zero`, durations and ranges become `0m`.
+pub fn carry_over_lines(previous: &str, existing: &[String], zero: bool) -> Vec<String> {
+    let mut seen: Vec<String> = existing.iter().map(|line| line.trim().to_owned()).collect();
+    let day = parse_day(previous);
+
+    let mut carried = Vec::new();
+    for entry in &day.entries {
+        let text = &day.lines[entry.line];
+        let line = if zero {
+            zero_duration(text, entry)
+        } else {
+            text.trim_end().to_owned()
+        };
+        if !seen.iter().any(|s| *s == line.trim()) {
+            seen.push(line.trim().to_owned());
+            carried.push(line);
+        }
+    }
+    carried
+}
+
+/// Rewrite an entry's line with a `0m` duration, keeping its indentation and
+/// list marker
+fn zero_duration(text: &str, entry: &Entry) -> String {
+    let body = text.trim_start();
+    let indent = &text[..text.len() - body.len()];
+    let marker = ["- ", "* ", "+ "]
+        .into_iter()
+        .find(|marker| body.starts_with(marker))
+        .unwrap_or("");
+    format!("{}{}{}: 0m", indent, marker, entry.description)
+}
+
+/// Start of the lines written by [`with_section_subtotals`]. It's fixed
+/// rather than translated so re-runs always find them, and such lines never
+/// parse as entries.
+const SUBTOTAL: &str = "Subtotal:";
+
+/// Add or refresh a `Subtotal: 1h30m` line at the end of each `##` (or
+/// deeper) section holding entries, dropping stale ones from sections that no
+/// longer do
+pub fn with_section_subtotals(lines: &[String]) -> Vec<String> {
+    let is_subtotal = |line: &str| line.trim().starts_with(SUBTOTAL);
+    let is_section = |line: &str| line.trim_start().starts_with("##");
+    let is_heading = |line: &str| line.trim_start().starts_with('#');
+
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        if !is_section(&lines[i]) {
+            out.push(lines[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let end = lines[i + 1..]
+            .iter()
+            .position(|line| is_heading(line))
+            .map_or(lines.len(), |n| i + 1 + n);
+        let mut section: Vec<String> = lines[i..end]
+            .iter()
+            .filter(|line| !is_subtotal(line))
+            .cloned()
+            .collect();
+
+        let entries: Vec<Entry> = section
+            .iter()
+            .enumerate()
+            .filter_map(|(n, line)| parse_entry(n, line))
+            .collect();
+        if !entries.is_empty() {
+            // After the last non-blank line, so blank lines before the next
+            // heading stay put
+            let at = section
+                .iter()
+                .rposition(|line| !line.trim().is_empty())
+                .map_or(section.len(), |n| n + 1);
+            let subtotal = format!("{} {}", SUBTOTAL, format_precise(total_seconds(&entries)));
+            section.insert(at, subtotal);
+        }
+
+        out.extend(section);
+        i = end;
+    }
+    out
+}
+
+/// Trailing `h`/`m`/`s` duration as seconds, with the byte offset it starts at
+fn unit_duration(text: &str) -> Option<(i64, usize)> {
+    let caps = DURATION.captures(text)?;
+    if caps.get(1).is_none() && caps.get(2).is_none() && caps.get(3).is_none() {
+        return None;
+    }
+    let hours = caps
+        .get(1)
+        .map_or(Some(0.0), |h| h.as_str().parse::<f64>().ok())?;
+    let mins = caps
+        .get(2)
+        .map_or(Some(0), |m| m.as_str().parse::<i64>().ok())?;
+    let secs = caps
+        .get(3)
+        .map_or(Some(0), |s| s.as_str().parse::<i64>().ok())?;
+    let minutes = (hours * 60.0).round() as i64 + mins;
+    Some((minutes * 60 + secs, caps.get(0)?.start()))
+}
+
+thread_local! {
+    /// The compiled `decimal_hours_pattern`, with the source it was built from
+    static DECIMAL_HOURS: RefCell<Option<(String, Option<Regex>)>> = const { RefCell::new(None) };
+}
+
+/// Trailing plain hours such as `2.5`, per the `decimal_hours_pattern`
+/// option (its first capture group is the hours), as minutes with the byte
+/// offset the match starts at. Off when the option is unset or invalid.
+fn decimal_hours(text: &str) -> Option<(i64, usize)> {
+    let pattern = options::get().decimal_hours_pattern.clone()?;
+    DECIMAL_HOURS.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.as_ref().is_none_or(|(source, _)| *source != pattern) {
+            let regex = Regex::new(&pattern).ok();
+            *cache = Some((pattern, regex));
+        }
+        let regex = cache.as_ref()?.1.as_ref()?;
+
+        let caps = regex.captures(text)?;
+        let hours = caps.get(1)?.as_str().parse::<f64>().ok()?;
+        Some(((hours * 60.0).round() as i64, caps.get(0)?.start()))
+    })
+}
+
+/// Map output lines (e.g. of the formatted preview) back to the source lines
+/// of `entries`, matching on the entry description. Each entry is used at
+/// most once, in order; lines showing no entry are left out.
+pub fn map_entry_lines(entries: &[Entry], output: &str) -> HashMap<usize, usize> {
+    let mut entries: Vec<&Entry> = entries
+        .iter()
+        .filter(|e| !e.description.is_empty())
+        .collect();
+
+    let mut mapping = HashMap::new();
+    for (output_line, text) in output.lines().enumerate() {
+        if let Some(i) = entries.iter().position(|e| text.contains(&e.description)) {
+            mapping.insert(output_line, entries.remove(i).line);
+        }
+    }
+    mapping
+}
+
+/// Sum of all entry durations in minutes
+pub fn total_minutes(entries: &[Entry]) -> i64 {
+    entries.iter().map(|e| e.minutes).sum()
+}
+
+/// Sum of the entries' durations in seconds, seconds parts included
+pub fn total_seconds(entries: &[Entry]) -> i64 {
+    entries.iter().map(Entry::duration_seconds).sum()
+}
+
+/// Split entry durations into `(billable, non_billable)` seconds: an entry is
+/// billable when `marker` appears anywhere in its description
+pub fn billable_seconds(entries: &[Entry], marker: &str) -> (i64, i64) {
+    entries.iter().fold((0, 0), |(billable, other), e| {
+        if e.description.contains(marker) {
+            (billable + e.duration_seconds(), other)
+        } else {
+            (billable, other + e.duration_seconds())
+        }
+    })
+}
+
+/// Format minutes like the formatter does: `2h`, `45m`, `1h15m`
+pub fn format_duration(minutes: i64) -> String {
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    // Only the hours can grow past three digits, see `number_locale`
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{sign}{m}m"),
+        (h, 0) => format!("{sign}{}h", format_number(h)),
+        (h, m) => format!("{sign}{}h{m}m", format_number(h)),
+    }
+}
+
+/// Format a plugin-computed duration given in seconds: `1h30m15s` while
+/// seconds are shown (see `show_seconds`), otherwise truncated to minutes
+/// like [`format_duration`]
+pub fn format_precise(seconds: i64) -> String {
+    if !state::with(|s| s.show_seconds) {
+        return format_duration(seconds / 60);
+    }
+
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    let parts = [
+        (seconds / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let out: String = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", format_number(*value), unit))
+        .collect();
+    if out.is_empty() {
+        "0s".to_owned()
+    } else {
+        format!("{sign}{out}")
+    }
+}
+
+/// Format a difference in seconds with an explicit sign, like
+/// [`format_precise`]: `+1h`, `-30m`, `±0m`
+pub fn format_delta(seconds: i64) -> String {
+    // Sign what's shown, so a few seconds never read as `+0m`
+    let shown = if state::with(|s| s.show_seconds) {
+        seconds
+    } else {
+        seconds / 60 * 60
+    };
+    match shown {
+        0 => format!("±{}", format_precise(0)),
+        s if s > 0 => format!("+{}", format_precise(s)),
+        s => format_precise(s),
+    }
+}
+
+fn clock_minutes(hours: &str, minutes: &str) -> Option<i64> {
+    let hours = hours.parse::<i64>().ok()?;
+    let minutes = minutes.parse::<i64>().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Strip list markers and leftover separators around a description
+fn clean_description(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("- ")
+        .or_else(|| text.strip_prefix("* "))
+        .or_else(|| text.strip_prefix("+ "))
+        .unwrap_or(text);
+    text.trim_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '-' | ',' | '|'))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,260 @@
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+use nvim_oxi::Array;
+use regex::Regex;
+use serde::Serialize;
+use time::Date;
+
+use crate::aggregate::{load_day, week_start_day};
+use crate::dates::{days_between, format_date, resolve_date, today, week_range};
+use crate::entries::parse_entries;
+use crate::projects::line_project;
+use crate::utils::buffer_lines;
+
+/// A parsed entry as exported to CSV, JSON and Lua
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportEntry {
+    /// Day of the entry's file, `YYYY-MM-DD`
+    pub date: String,
+    pub description: String,
+    pub minutes: i64,
+    /// Project tag, per the `project_tag_pattern` option
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Whether the entry carries the `billable_marker`; unset without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+}
+
+impl ExportEntry {
+    /// The entry as a Lua table, with the same keys as the JSON export
+    pub fn to_dictionary(&self) -> Dictionary {
+        let mut dict = Dictionary::from_iter([
+            ("date", Object::from(self.date.as_str())),
+            ("description", Object::from(self.description.as_str())),
+            ("minutes", Object::from(self.minutes)),
+        ]);
+        if let Some(project) = &self.project {
+            dict.insert("project", project.as_str());
+        }
+        if let Some(billable) = self.billable {
+            dict.insert("billable", billable);
+        }
+        dict
+    }
+}
+
+/// Every entry of `dates`, in order; days without a file are skipped
+pub fn export_entries(config: &Config, dates: impl IntoIterator<Item = Date>) -> Vec<ExportEntry> {
+    let options = options::get();
+    // An invalid pattern is reported by `TimeTrackingProjects`; here it just
+    // means no project tags
+    let pattern = Regex::new(&options.project_tag_pattern).ok();
+
+    let mut exported = Vec::new();
+    for date in dates {
+        let Some(content) = load_day(config, date) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        for entry in parse_entries(&content) {
+            exported.push(ExportEntry {
+                date: format_date(date, "%Y-%m-%d"),
+                project: pattern
+                    .as_ref()
+                    .and_then(|pattern| line_project(lines[entry.line], pattern)),
+                billable: options
+                    .billable_marker
+                    .as_deref()
+                    .map(|marker| entry.description.contains(marker)),
+                description: entry.description,
+                minutes: entry.minutes,
+            });
+        }
+    }
+    exported
+}
+
+/// Every entry of `dates` as CSV with a `date,description,minutes` header
+pub fn render_csv(config: &Config, dates: impl IntoIterator<Item = Date>) -> String {
+    let mut out = String::from("date,description,minutes\n");
+    for entry in export_entries(config, dates) {
+        out.push_str(&format!(
+            "{},{},{}\n",
+            entry.date,
+            csv_field(&entry.description),
+            entry.minutes
+        ));
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Every entry of `dates` as a JSON array of [`ExportEntry`] objects
+pub fn render_json(config: &Config, dates: impl IntoIterator<Item = Date>, pretty: bool) -> String {
+    let entries = export_entries(config, dates);
+    let json = if pretty {
+        serde_json::to_string_pretty(&entries)
+    } else {
+        serde_json::to_string(&entries)
+    };
+    json.expect("entries serialize to JSON")
+}
+
+/// [`export_entries`] for the current week as a list of tables, for Lua callers
+pub fn week_entries_fn(config: &'static Config) -> Array {
+    let (start, end) = week_range(today(), week_start_day(config), 0);
+    export_entries(config, days_between(start, end))
+        .iter()
+        .map(ExportEntry::to_dictionary)
+        .collect()
+}
+
+/// Write the entries of the current week, or of `start end`, as CSV
+pub fn export_csv_fn(config: &'static Config, args: &[String]) -> Result<()> {
+    let Some((start, end, path)) = export_target(config, args, "csv") else {
+        return Ok(());
+    };
+    write_export(&path, &render_csv(config, days_between(start, end)))
+}
+
+/// Write the entries of the current week, or of `start end`, as JSON;
+/// `--pretty` indents it
+pub fn export_json_fn(config: &'static Config, args: &[String]) -> Result<()> {
+    let pretty = args.iter().any(|arg| arg == "--pretty");
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != "--pretty")
+        .cloned()
+        .collect();
+
+    let Some((start, end, path)) = export_target(config, &args, "json") else {
+        return Ok(());
+    };
+    write_export(
+        &path,
+        &render_json(config, days_between(start, end), pretty),
+    )
+}
+
+/// On exit, write the preview's summary to the `export_on_exit` path, if
+/// there's a preview (or one closed this session) to take it from
+pub fn export_on_exit_fn(config: &'static Config) -> Result<()> {
+    let Some(template) = options::get().export_on_exit.clone() else {
+        return Ok(());
+    };
+
+    let content = match find_preview_buffer()? {
+        Some(buf) => Some(buffer_lines(&buf)?.join("\n")),
+        None => state::with(|s| s.closed_preview.as_ref().map(|c| c.content.clone())),
+    };
+    let Some(content) = content else {
+        return Ok(());
+    };
+
+    let Some(path) = exit_export_path(config, &template, today()) else {
+        return Ok(());
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, content + "\n"));
+    if let Err(e) = written {
+        log_error!("[time-tracking] failed to write {}: {}", path.display(), e);
+    }
+    Ok(())
+}
+
+/// Expand an `export_on_exit` template for `date`; relative paths are taken
+/// from the data directory
+pub fn exit_export_path(config: &Config, template: &str, date: Date) -> Option<PathBuf> {
+    let path = utils::expand_tilde(&format_date(date, template));
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(files::data_dir(config)?.join(path))
+    }
+}
+
+/// Resolve `[start end] [path]` export arguments. The range defaults to the
+/// current week and the path to `time-tracking-<start>-<end>.<extension>` in
+/// the data directory; a range that starts after it ends is rejected.
+fn export_target(
+    config: &Config,
+    args: &[String],
+    extension: &str,
+) -> Option<(Date, Date, PathBuf)> {
+    let today = today();
+    // Leading arguments are a range only if both parse as dates
+    let range = match args {
+        [start, end, ..] => resolve_date(start, today).zip(resolve_date(end, today)),
+        _ => None,
+    };
+    let (start, end, rest) = match range {
+        Some((start, end)) if start > end => {
+            log_error!(
+                "[time-tracking] range start {} is after its end {}",
+                format_date(start, "%Y-%m-%d"),
+                format_date(end, "%Y-%m-%d")
+            );
+            return None;
+        }
+        Some((start, end)) => (start, end, &args[2..]),
+        None => {
+            let (start, end) = week_range(today, week_start_day(config), 0);
+            (start, end, args)
+        }
+    };
+
+    let path = match rest {
+        [] => {
+            let Some(dir) = files::data_dir(config) else {
+                log_error!("[time-tracking] no data directory to export to");
+                return None;
+            };
+            dir.join(format!(
+                "time-tracking-{}-{}.{}",
+                format_date(start, "%Y-%m-%d"),
+                format_date(end, "%Y-%m-%d"),
+                extension
+            ))
+        }
+        [path] => utils::expand_tilde(path),
+        _ => {
+            log_error!("[time-tracking] export expects [start end] [path]");
+            return None;
+        }
+    };
+
+    Some((start, end, path))
+}
+
+/// Write an export, creating its directory if needed, and report where it
+/// went
+fn write_export(path: &Path, contents: &str) -> Result<()> {
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(path, contents));
+    if let Err(e) = written {
+        log_error!("[time-tracking] failed to write {}: {}", path.display(), e);
+        return Ok(());
+    }
+
+    api::notify(
+        &format!("[time-tracking] wrote {}", path.display()),
+        LogLevel::Info,
+        &Default::default(),
+    )?;
+    Ok(())
+}
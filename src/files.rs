@@ -0,0 +1,255 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use flate2::read::GzDecoder;
+use regex::Regex;
+use time::{Date, Month};
+use time_tracking_cli::Config;
+
+use crate::{
+    dates::{RELATIVE_DATE_TOKENS, format_date},
+    options,
+    utils::{cached_data_dir, strip_verbatim_prefix},
+};
+
+/// How many recent file dates to offer when completing a date argument
+const MAX_DATE_COMPLETIONS: usize = 14;
+
+/// The configured data directory, canonicalized, if it exists
+pub fn data_dir(config: &Config) -> Option<PathBuf> {
+    cached_data_dir(config)
+}
+
+/// Path of the daily file for `date`, built from the `daily_file_pattern` option
+pub fn daily_file_path(config: &Config, date: Date) -> Option<PathBuf> {
+    let relative = format_date(date, &options::get().daily_file_pattern);
+    Some(data_dir(config)?.join(relative))
+}
+
+/// Suffix of archived (gzip-compressed) daily files, e.g. `2024-01-01.md.gz`
+const ARCHIVE_EXTENSION: &str = ".gz";
+
+/// Read the daily file for `date`, falling back to its gzipped archive
+pub fn read_daily_file(config: &Config, date: Date) -> Option<String> {
+    let path = daily_file_path(config, date)?;
+    if let Ok(content) = fs::read_to_string(&path) {
+        return Some(content);
+    }
+
+    let mut archived = path.into_os_string();
+    archived.push(ARCHIVE_EXTENSION);
+    let mut content = String::new();
+    GzDecoder::new(fs::File::open(archived).ok()?)
+        .read_to_string(&mut content)
+        .ok()?;
+    Some(content)
+}
+
+/// Create `path` (and its parent directories) with `content`, unless it
+/// already exists. Creation is atomic, so when several Neovim instances race
+/// to create the same file exactly one writes it and the others leave it be.
+/// Returns whether this call created it.
+pub fn create_new_file(path: &Path, content: &str) -> io::Result<bool> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    invalidate_listing();
+    file.write_all(content.as_bytes())?;
+    Ok(true)
+}
+
+/// Recover the date from a daily file's path, if it matches `daily_file_pattern`
+pub fn date_from_path(config: &Config, path: &Path) -> Option<Date> {
+    let data_dir = data_dir(config)?;
+    let relative = path.strip_prefix(&data_dir).ok()?;
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    // Archived days are the same file, gzipped
+    let relative = relative
+        .strip_suffix(ARCHIVE_EXTENSION)
+        .unwrap_or(&relative);
+
+    let captures = pattern_regex(&options::get().daily_file_pattern)?.captures(relative)?;
+    let year = captures.name("Y")?.as_str().parse::<i32>().ok()?;
+    let month = captures.name("m")?.as_str().parse::<u8>().ok()?;
+    let day = captures.name("d")?.as_str().parse::<u8>().ok()?;
+
+    Date::from_calendar_date(year, Month::try_from(month).ok()?, day).ok()
+}
+
+/// Dates that have a daily file in the data directory, most recent first
+pub fn tracked_dates(config: &Config) -> Vec<Date> {
+    let Some(data_dir) = data_dir(config) else {
+        return Vec::new();
+    };
+
+    let files = listing(&data_dir);
+
+    let mut dates: Vec<Date> = files
+        .iter()
+        .filter_map(|path| date_from_path(config, path))
+        .collect();
+    dates.sort_unstable_by(|a, b| b.cmp(a));
+    dates.dedup();
+    dates
+}
+
+/// Every tracking file under the data directory, sorted, as the canonical
+/// paths buffer detection compares against. Symlinked files count when they
+/// resolve into the data directory, like a buffer opened through the link.
+pub fn tracking_files(config: &Config) -> Vec<PathBuf> {
+    let Some(data_dir) = data_dir(config) else {
+        return Vec::new();
+    };
+
+    let files = listing(&data_dir);
+
+    let options = options::get();
+    let mut tracking: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .map(|path| strip_verbatim_prefix(&path))
+        .filter(|path| {
+            path.starts_with(&data_dir)
+                && matches!(path.extension(), Some(ext)
+                    if options.is_tracking_extension(&ext.to_string_lossy()))
+        })
+        .collect();
+    tracking.sort_unstable();
+    tracking.dedup();
+    tracking
+}
+
+/// [`tracking_files`] for browsing: daily files newest first, then any other
+/// tracking files by path
+pub fn tracking_files_by_date(config: &Config) -> Vec<PathBuf> {
+    let mut files: Vec<(Option<Date>, PathBuf)> = tracking_files(config)
+        .into_iter()
+        .map(|path| (date_from_path(config, &path), path))
+        .collect();
+    // Stable, so undated files keep their path order
+    files.sort_by_key(|(date, _)| std::cmp::Reverse(*date));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Completion candidates for a date argument: relative tokens plus the most
+/// recent dates that have a daily file, filtered by what's been typed so far
+pub fn complete_dates(config: &Config, arg_lead: &str) -> Vec<String> {
+    RELATIVE_DATE_TOKENS
+        .iter()
+        .map(|token| (*token).to_owned())
+        .chain(
+            tracked_dates(config)
+                .into_iter()
+                .take(MAX_DATE_COMPLETIONS)
+                .map(|date| format_date(date, "%Y-%m-%d")),
+        )
+        .filter(|candidate| candidate.starts_with(arg_lead))
+        .collect()
+}
+
+thread_local! {
+    /// The last listing of the data directory, with where and when it was taken
+    static LISTING: RefCell<Option<(PathBuf, Instant, Vec<PathBuf>)>> = const { RefCell::new(None) };
+}
+
+/// Every file under `data_dir`, re-walked at most once per `rescan_interval`
+/// so repeated completions and pickers don't re-read a large or remote tree
+fn listing(data_dir: &Path) -> Vec<PathBuf> {
+    let interval = Duration::from_millis(options::get().rescan_interval.max(0) as u64);
+    let cached = LISTING.with(|listing| {
+        listing
+            .borrow()
+            .as_ref()
+            .filter(|(dir, scanned, _)| dir == data_dir && scanned.elapsed() < interval)
+            .map(|(_, _, files)| files.clone())
+    });
+    if let Some(files) = cached {
+        return files;
+    }
+
+    let mut files = Vec::new();
+    walk(data_dir, &mut files);
+    LISTING.with(|listing| {
+        *listing.borrow_mut() = Some((data_dir.to_path_buf(), Instant::now(), files.clone()))
+    });
+    files
+}
+
+/// Forget the cached data directory listing, e.g. after the plugin created a
+/// file, so the next lookup re-walks it
+pub fn invalidate_listing() {
+    LISTING.with(|listing| *listing.borrow_mut() = None);
+}
+
+/// Recursively collect the regular files under `dir`
+pub(crate) fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(ft) if ft.is_dir() => walk(&path, out),
+            Ok(_) => out.push(path),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Turn a daily file pattern like `%Y/%m/%Y-%m-%d.md` into an anchored regex
+/// that captures the year, month and day
+fn pattern_regex(pattern: &str) -> Option<Regex> {
+    let mut regex = String::from("^");
+    let mut seen = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        let token = chars.next()?;
+        if token == '%' {
+            literal.push('%');
+            continue;
+        }
+
+        regex.push_str(&regex::escape(&literal));
+        literal.clear();
+
+        // Repeated fields must match the same value; only capture the first
+        let group = match token {
+            'Y' | 'm' | 'd' if !seen.contains(&token) => {
+                seen.push(token);
+                let width = if token == 'Y' { 4 } else { 2 };
+                format!(r"(?P<{token}>\d{{{width}}})")
+            }
+            'Y' => r"\d{4}".to_owned(),
+            'm' | 'd' | 'e' => r"[ \d]\d".to_owned(),
+            'j' => r"\d{3}".to_owned(),
+            _ => r"[^/]+?".to_owned(),
+        };
+        regex.push_str(&group);
+    }
+
+    regex.push_str(&regex::escape(&literal));
+    regex.push('$');
+    Regex::new(&regex).ok()
+}
@@ -0,0 +1,40 @@
+use super::*;
+
+use crate::dates::{format_date, resolve_date, today};
+
+/// Insert the configured date header at the top of the current tracking buffer
+///
+/// Uses today's date unless `date_arg` (an ISO date or relative token like
+/// `yesterday`/`-1`) overrides it. Does nothing if the header is already present.
+pub fn insert_header_fn(config: &'static Config, date_arg: Option<&str>) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let today = today();
+    let date = match date_arg.map(str::trim).filter(|arg| !arg.is_empty()) {
+        Some(arg) => match resolve_date(arg, today) {
+            Some(date) => date,
+            None => {
+                log_error!("[time-tracking] invalid date '{}'", arg);
+                return Ok(());
+            }
+        },
+        None => today,
+    };
+
+    let header = format_date(date, &options::get().header_format);
+
+    let mut buf = api::get_current_buf();
+    let line_count = buf.line_count()?;
+    let already_present = buf
+        .get_lines(0..line_count, false)?
+        .any(|line| line.to_string_lossy() == header);
+    if already_present {
+        return Ok(());
+    }
+
+    buf.set_lines(0..0, false, [header])?;
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::cell::Cell;
+
+use nvim_oxi::{Array, Dictionary, Object, api, conversion::FromObject};
+
+use crate::entries::Entry;
+use crate::log_warn;
+
+/// Calls the `parse_hook` given to `setup()`, which the Lua side keeps since
+/// functions can't travel through `vim.g`
+const PARSE_HOOK_LUA: &str = r#"require("time-tracking-nvim").parse_hook(_A)"#;
+
+thread_local! {
+    /// Whether we've already warned about a failing `parse_hook`
+    static HOOK_WARNED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forget that the `parse_hook` failed, so the next failure is reported again
+pub fn clear_hook_warning() {
+    HOOK_WARNED.with(|warned| warned.set(false));
+}
+
+/// The entries the `parse_hook` finds in `content`, or `None` if it fails,
+/// so the built-in parser can take over
+pub fn hook_entries(content: &str) -> Option<Vec<Entry>> {
+    match api::call_function::<_, Array>("luaeval", (PARSE_HOOK_LUA, content)) {
+        Ok(list) => Some(
+            list.into_iter()
+                .filter_map(|item| hook_entry(content, item))
+                .collect(),
+        ),
+        Err(e) => {
+            if !HOOK_WARNED.with(|warned| warned.replace(true)) {
+                log_warn!(
+                    "[time-tracking] parse_hook failed, using the built-in parser: {}",
+                    e
+                );
+            }
+            None
+        }
+    }
+}
+
+/// One `{ description, minutes }` table from the hook, keyed or positional.
+/// An optional 1-based `line` places the entry; without one it goes on the
+/// first line mentioning the description, or else the first line. Entries
+/// placed outside `content` (any entry, when it's empty) are dropped, since
+/// callers index the content's lines with them.
+fn hook_entry(content: &str, item: Object) -> Option<Entry> {
+    let (description, minutes, line) = match Dictionary::from_object(item.clone()) {
+        Ok(dict) => {
+            let field = |key: &str| {
+                dict.iter()
+                    .find(|(k, _)| k.to_string_lossy() == key)
+                    .map(|(_, v)| v.clone())
+            };
+            let line = field("line").and_then(|line| i64::from_object(line).ok());
+            (field("description")?, field("minutes")?, line)
+        }
+        Err(_) => {
+            let mut fields = Array::from_object(item).ok()?.into_iter();
+            (fields.next()?, fields.next()?, None)
+        }
+    };
+
+    let description = String::from_object(description).ok()?;
+    let seconds = match i64::from_object(minutes.clone()) {
+        Ok(minutes) => minutes * 60,
+        Err(_) => (f64::from_object(minutes).ok()? * 60.0).round() as i64,
+    };
+    let line = match line {
+        Some(line) => usize::try_from(line.checked_sub(1)?).ok()?,
+        None => content
+            .lines()
+            .position(|text| text.contains(description.as_str()))
+            .unwrap_or(0),
+    };
+    if line >= content.lines().count() {
+        return None;
+    }
+
+    Some(Entry {
+        line,
+        description,
+        minutes: seconds / 60,
+        seconds: seconds % 60,
+    })
+}
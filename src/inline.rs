@@ -0,0 +1,115 @@
+use super::*;
+
+use nvim_oxi::api::{
+    opts::{OptionScope, SetExtmarkOpts},
+    types::ExtmarkVirtTextPosition,
+};
+
+use crate::entries::format_precise;
+use crate::utils::{namespace, preserving_view};
+
+/// Namespace holding the inline durations, so clearing never touches other
+/// plugins' extmarks
+const INLINE_NAMESPACE: &str = "time_tracking_inline";
+
+/// Window variable marking a winbar we set, so only ours is ever reset
+const WINBAR_VAR: &str = "time_tracking_winbar";
+
+/// Highlight group for the inline durations
+const INLINE_HL: &str = "Comment";
+
+/// Turn the inline durations and winbar total on or off; what
+/// `TimeTrackingToggle` does with `mode = "inline"`
+pub fn toggle_inline_fn(config: &'static Config) -> Result<()> {
+    let enabled = state::with(|s| {
+        s.inline = !s.inline;
+        s.inline
+    });
+
+    if enabled {
+        update_inline_fn(config)
+    } else {
+        clear_all_inline()
+    }
+}
+
+/// Move over to the `mode` just loaded by `TimeTrackingReload`: close the
+/// previews for inline mode, or remove the inline annotations for the preview
+pub fn switch_display_mode_fn(config: &'static Config) -> Result<()> {
+    let inline = options::get().is_inline();
+    state::with(|s| s.inline = inline);
+    if inline {
+        close_all_previews()?;
+        update_inline_fn(config)
+    } else {
+        clear_all_inline()
+    }
+}
+
+/// Remove the inline durations and winbar totals everywhere
+pub fn clear_all_inline() -> Result<()> {
+    let ns = namespace(INLINE_NAMESPACE);
+    for mut buf in api::list_bufs() {
+        buf.clear_namespace(ns, ..)?;
+    }
+    for win in api::list_wins() {
+        clear_winbar(win)?;
+    }
+    Ok(())
+}
+
+/// Show each entry's duration at the end of its line, and the running total
+/// in the winbar, for the current tracking buffer
+pub fn update_inline_fn(config: &'static Config) -> Result<()> {
+    if !options::get().is_inline() || !state::with(|s| s.inline) || !is_time_tracking_file(config)?
+    {
+        return Ok(());
+    }
+
+    preserving_view(|| {
+        let day = parse_buffer_day()?;
+        let mut buf = api::get_current_buf();
+        let ns = namespace(INLINE_NAMESPACE);
+        buf.clear_namespace(ns, ..)?;
+
+        for entry in &day.entries {
+            let opts = SetExtmarkOpts::builder()
+                .virt_text([(format_precise(entry.duration_seconds()), INLINE_HL)])
+                .virt_text_pos(ExtmarkVirtTextPosition::Eol)
+                .build();
+            buf.set_extmark(ns, entry.line, 0, &opts)?;
+        }
+
+        let total = total_message(config, &day.entries);
+        let mut win = api::get_current_win();
+        // The winbar is a statusline expression, where `%` is special
+        api::set_option_value("winbar", total.replace('%', "%%"), &winbar_opts(&win))?;
+        win.set_var(WINBAR_VAR, true)?;
+
+        Ok(())
+    })
+}
+
+/// Remove the inline durations and winbar total from the current buffer and
+/// window
+pub fn clear_inline_fn() -> Result<()> {
+    api::get_current_buf().clear_namespace(namespace(INLINE_NAMESPACE), ..)?;
+    clear_winbar(api::get_current_win())
+}
+
+/// Reset the window-local winbar, if it's one we set
+fn clear_winbar(mut win: Window) -> Result<()> {
+    if !win.get_var::<bool>(WINBAR_VAR).unwrap_or(false) {
+        return Ok(());
+    }
+    // An empty local value falls back to the user's global winbar
+    api::set_option_value("winbar", "", &winbar_opts(&win))?;
+    win.del_var(WINBAR_VAR)
+}
+
+fn winbar_opts(win: &Window) -> api::opts::OptionOpts {
+    OptionOptsBuilder::default()
+        .scope(OptionScope::Local)
+        .win(win.clone())
+        .build()
+}
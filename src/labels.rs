@@ -0,0 +1,82 @@
+use crate::options;
+
+/// English defaults for the labels the plugin renders itself
+const DEFAULT_LABELS: &[(&str, &str)] = &[
+    ("total", "Total"),
+    ("today", "Today"),
+    ("yesterday", "Yesterday"),
+    ("tomorrow", "Tomorrow"),
+    ("week", "Week"),
+    ("no_data", "no data"),
+    ("delta", "Delta"),
+    ("projects", "Projects"),
+    ("untagged", "Untagged"),
+    ("also", "also"),
+    ("billable", "Billable"),
+    ("non_billable", "Non-billable"),
+    ("ytd", "year to date"),
+    ("overlap", "Overlapping time ranges"),
+    ("line", "line"),
+];
+
+/// Look up the display text for a plugin-generated label.
+///
+/// Uses the `i18n` option when it has a translation for `key`, otherwise the
+/// English default (or the key itself if it isn't a known label).
+pub fn label(key: &str) -> String {
+    if let Some(translated) = options::get().i18n.get(key) {
+        return translated.clone();
+    }
+
+    DEFAULT_LABELS
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| (*v).to_owned())
+        .unwrap_or_else(|| key.to_owned())
+}
+
+/// Format a plugin-computed integer with the grouping of the `number_locale`
+/// option (e.g. `1,234` for `en`, `1.234` for `de`). No grouping when unset.
+pub fn format_number(value: i64) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let separator = match options::get().number_locale.as_deref() {
+        Some(locale) => group_separator(locale),
+        None => None,
+    };
+
+    let grouped = match separator {
+        Some(sep) => {
+            let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+            for (i, c) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i) % 3 == 0 {
+                    out.push_str(sep);
+                }
+                out.push(c);
+            }
+            out
+        }
+        None => digits,
+    };
+
+    if value < 0 {
+        format!("-{grouped}")
+    } else {
+        grouped
+    }
+}
+
+/// Thousands separator for a locale like `en`, `en_US` or `de-DE`
+fn group_separator(locale: &str) -> Option<&'static str> {
+    let language = locale
+        .split(['_', '-', '.'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    match language.as_str() {
+        "en" | "ja" | "ko" | "zh" | "he" | "th" => Some(","),
+        "de" | "nl" | "it" | "es" | "pt" | "da" | "id" | "tr" | "el" => Some("."),
+        "fr" | "sv" | "nb" | "no" | "fi" | "pl" | "cs" | "ru" | "uk" => Some("\u{202F}"),
+        _ => None,
+    }
+}
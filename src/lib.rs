@@ -1,21 +1,62 @@
 use nvim_oxi::api::opts::OptionOptsBuilder;
+use nvim_oxi::api::types::{CommandArgs, CommandComplete, CommandNArgs, LogLevel};
 use nvim_oxi::api::{Buffer, Window};
 use nvim_oxi::schedule;
 use nvim_oxi::{
-    Dictionary, Function, Result,
+    Dictionary, Function, Object, Result,
     api::{
         self,
-        opts::{CreateAutocmdOpts, CreateCommandOpts},
+        opts::{BufDeleteOpts, CreateAutocmdOpts, CreateCommandOpts, ExecAutocmdsOpts},
     },
 };
 use time_tracking_cli::Config;
 
-use crate::utils::{any_tracking_visible, get_buffer_content, is_time_tracking_file};
+use crate::utils::{
+    PREVIEW_KEY_VAR, PREVIEW_VAR, any_tracking_visible, clear_detection_cache, find_preview_buffer,
+    find_preview_window, find_preview_window_in, find_preview_windows, get_buffer_content,
+    get_buffer_content_with, has_preview_var, is_time_tracking_file, macro_active, preview_key,
+};
 
+pub mod aggregate;
+mod carry_over;
+mod cli;
+pub mod dates;
+mod diagnostics;
+pub mod entries;
+pub mod export;
+pub mod files;
+mod header;
+mod hook;
+mod inline;
+pub mod labels;
+mod navigate;
+pub mod options;
 mod preview;
+pub mod projects;
+mod sections;
+mod signs;
+mod sort;
+pub mod state;
+pub mod summary;
+pub mod tags;
 pub mod utils;
 
-use preview::*;
+pub use summary::{config_for_extension, formatter_names, summarize, summarize_with, with_footer};
+
+use aggregate::*;
+use carry_over::*;
+use cli::*;
+use diagnostics::*;
+use export::*;
+use header::*;
+use inline::*;
+use navigate::*;
+pub use preview::*;
+use projects::*;
+use sections::*;
+use signs::*;
+use sort::*;
+use tags::*;
 
 #[macro_export]
 macro_rules! log_info {
@@ -34,6 +75,14 @@ macro_rules! log_error {
     };
 }
 
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        use nvim_oxi::api::types::LogLevel;
+        let _ = nvim_oxi::api::notify(&format!($($arg)*), LogLevel::Warn, &Default::default());
+    };
+}
+
 /// Plugin to provide time tracking previews while editing in Neovim.
 #[nvim_oxi::plugin]
 fn time_tracking_nvim() -> Result<Dictionary> {
@@ -43,13 +92,37 @@ fn time_tracking_nvim() -> Result<Dictionary> {
     time_tracking_with_config(config)
 }
 
+/// Set the parts of the runtime state that start out from the options and
+/// the CLI config
+fn init_state(config: &Config, s: &mut state::State) {
+    let options = options::get();
+    s.pinned = options.pin_preview;
+    s.signs = options.signs;
+    s.noedit = config.noedit;
+    s.relative_dates = options.relative_dates;
+    s.show_seconds = options.show_seconds;
+    s.inline = options.is_inline();
+}
+
 /// inner function which accepts `config` for testing
 pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary> {
-    // Create command to toggle preview
-    let toggle_preview = Function::from_fn(move |_| toggle_preview_fn(config));
+    options::load();
+    state::with(|s| init_state(config, s));
+    validate_week_start_day(config);
+    validate_tag_pattern();
+
+    // Create command to toggle preview (the inline annotations in inline mode)
+    let toggle_preview = Function::from_fn(move |_| {
+        if options::get().is_inline() {
+            toggle_inline_fn(config)
+        } else {
+            toggle_preview_fn(config)
+        }
+    });
 
     // Create command to update preview (for auto-updating)
     let update_preview = Function::from_fn(move |_| update_preview_fn(config));
+    let update_debounced = Function::from_fn(move |_| debounced_update_fn(config));
 
     // Create command to auto-open preview
     let auto_open = Function::from_fn(move |_| auto_open_preview(config));
@@ -58,9 +131,35 @@ pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary>
     let auto_close = Function::from_fn(move |_| auto_close_preview(config));
 
     // Create command to manually close preview window
-    let close_preview_cmd = Function::from_fn(move |_| close_preview());
+    let close_preview_cmd = Function::from_fn(move |_| -> Result<()> {
+        close_preview()?;
+        set_tab_wants_preview(false);
+        Ok(())
+    });
+
+    // Create command to close the preview in every tabpage
+    let close_all_cmd = Function::from_fn(move |_| -> Result<()> {
+        close_all_previews()?;
+        state::with(|s| {
+            for wanted in s.tab_wants_preview.values_mut() {
+                *wanted = false;
+            }
+        });
+        set_tab_wants_preview(false);
+        Ok(())
+    });
+
+    // Create command to restore the preview state remembered for a tabpage
+    let restore_tab = Function::from_fn(move |_| restore_tab_preview(config));
+
+    // Create command to wipe the preview buffer(s) on exit
+    let wipe_preview = Function::from_fn(move |_| wipe_preview_buffers());
 
     let maybe_close_if_invisible = Function::from_fn(move |_| -> Result<()> {
+        // A pinned preview stays open even when no tracking file is visible
+        if state::with(|s| s.pinned) {
+            return Ok(());
+        }
         if !any_tracking_visible(config)? {
             close_preview()?;
         }
@@ -81,6 +180,22 @@ pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary>
             .build(),
     )?;
 
+    // Keep the preview's proportion as splits come and go
+    let resize_preview = Function::from_fn(move |_| resize_preview_fn());
+
+    api::create_user_command(
+        "TimeTrackingResizePreview",
+        resize_preview,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["WinNew", "WinClosed"],
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingResizePreview")
+            .build(),
+    )?;
+
     // Register commands
     api::create_user_command(
         "TimeTrackingToggle",
@@ -94,6 +209,12 @@ pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary>
         &CreateCommandOpts::builder().build(),
     )?;
 
+    api::create_user_command(
+        "TimeTrackingUpdateDebounced",
+        update_debounced,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
     api::create_user_command(
         "TimeTrackingAutoOpen",
         auto_open,
@@ -112,28 +233,570 @@ pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary>
         &CreateCommandOpts::builder().build(),
     )?;
 
-    // Set up autocommands for live updates on markdown files
+    api::create_user_command(
+        "TimeTrackingCloseAll",
+        close_all_cmd,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingWipePreview",
+        wipe_preview,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create commands to pin/unpin the preview against auto-closing
+    let pin = Function::from_fn(move |_| -> Result<()> {
+        state::with(|s| s.pinned = true);
+        Ok(())
+    });
+    let unpin = Function::from_fn(move |_| -> Result<()> {
+        state::with(|s| s.pinned = false);
+        Ok(())
+    });
+
+    api::create_user_command(
+        "TimeTrackingPin",
+        pin,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingUnpin",
+        unpin,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create commands to pause/resume live updates without closing the preview
+    let pause = Function::from_fn(move |_| -> Result<()> {
+        state::with(|s| s.paused = true);
+        Ok(())
+    });
+    let resume = Function::from_fn(move |_| resume_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingPause",
+        pause,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingResume",
+        resume,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to insert the date header, optionally for a given date
+    let insert_header =
+        Function::from_fn(move |args: CommandArgs| insert_header_fn(config, args.args.as_deref()));
+
+    api::create_user_command(
+        "TimeTrackingInsertHeader",
+        insert_header,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create commands to override the prefix/suffix at runtime (no argument resets)
+    let set_prefix =
+        Function::from_fn(move |args: CommandArgs| set_prefix_fn(config, args.args.as_deref()));
+    let set_suffix =
+        Function::from_fn(move |args: CommandArgs| set_suffix_fn(config, args.args.as_deref()));
+
+    api::create_user_command(
+        "TimeTrackingSetPrefix",
+        set_prefix,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingSetSuffix",
+        set_suffix,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .build(),
+    )?;
+
+    // Create command to show a week's summary, offset from the current week
+    let week = Function::from_fn(move |args: CommandArgs| -> Result<()> {
+        match week_offset(&args) {
+            Ok(offset) => week_fn(config, offset),
+            Err(msg) => {
+                log_error!("[time-tracking] {}", msg);
+                Ok(())
+            }
+        }
+    });
+
+    api::create_user_command(
+        "TimeTrackingWeek",
+        week,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .count(0)
+            .build(),
+    )?;
+
+    // Create command to compare two days side by side
+    let compare = Function::from_fn(move |args: CommandArgs| compare_fn(config, &args.fargs));
+
+    api::create_user_command(
+        "TimeTrackingCompare",
+        compare,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::Any)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create command to total time per project tag for a day or date range
+    let projects = Function::from_fn(move |args: CommandArgs| projects_fn(config, &args.fargs));
+
+    api::create_user_command(
+        "TimeTrackingProjects",
+        projects,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::Any)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create command to total an explicit, inclusive date range
+    let range = Function::from_fn(move |args: CommandArgs| range_fn(config, &args.fargs));
+
+    api::create_user_command(
+        "TimeTrackingRange",
+        range,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::Any)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create command to total this year so far, month by month
+    let ytd = Function::from_fn(move |_| ytd_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingYTD",
+        ytd,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to flip the preview between day and week views
+    let toggle_view = Function::from_fn(move |_| toggle_view_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingToggleView",
+        toggle_view,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingRestoreTab",
+        restore_tab,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
     api::create_autocmd(
-        vec!["TextChanged", "TextChangedI"],
+        vec!["TabEnter"],
         &CreateAutocmdOpts::builder()
-            .command("TimeTrackingUpdate")
+            .command("TimeTrackingRestoreTab")
+            .build(),
+    )?;
+
+    // Create command to echo the active config for troubleshooting
+    let show_config = Function::from_fn(move |_| show_config_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingConfig",
+        show_config,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to post-process the current file with an external command
+    let run_cli = Function::from_fn(move |_| run_cli_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingRunCli",
+        run_cli,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to list the CLI's formatters
+    let list_formatters = Function::from_fn(move |_| list_formatters_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingFormatters",
+        list_formatters,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to return the plugin to its freshly-loaded state
+    let reset = Function::from_fn(move |_| reset_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingReset",
+        reset,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to re-read plugin options and re-detect tracking files
+    let reload = Function::from_fn(move |_| -> Result<()> {
+        let was_inline = options::get().is_inline();
+        options::load();
+        clear_detection_cache();
+        files::invalidate_listing();
+        if options::get().is_inline() != was_inline {
+            switch_display_mode_fn(config)?;
+        }
+        update_preview_fn(config)
+    });
+
+    api::create_user_command(
+        "TimeTrackingReload",
+        reload,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create commands to move into the preview and back out again
+    let focus = Function::from_fn(move |_| focus_preview_fn(config));
+    let blur = Function::from_fn(move |_| blur_preview_fn());
+
+    api::create_user_command(
+        "TimeTrackingPreviewFocus",
+        focus,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingPreviewBlur",
+        blur,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to jump from a preview line to its entry in the source
+    let goto_entry = Function::from_fn(move |_| goto_entry_fn());
+
+    api::create_user_command(
+        "TimeTrackingGotoEntry",
+        goto_entry,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to copy a preview entry's duration
+    let copy_entry = Function::from_fn(move |_| copy_entry_fn());
+
+    api::create_user_command(
+        "TimeTrackingCopyEntry",
+        copy_entry,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to browse the data directory
+    let open_data_dir = Function::from_fn(move |_| open_data_dir_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingOpenDataDir",
+        open_data_dir,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to open a day's file (creating it) along with its preview
+    let edit_day =
+        Function::from_fn(move |args: CommandArgs| edit_day_fn(config, args.args.as_deref()));
+
+    api::create_user_command(
+        "TimeTrackingEdit",
+        edit_day,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create command to show what changed at the last preview update
+    let delta = Function::from_fn(move |_| delta_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingDelta",
+        delta,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to pick a tracking file to open
+    let pick_file = Function::from_fn(move |_| files_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingFiles",
+        pick_file,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to create and open today's file on startup
+    let auto_create_today = Function::from_fn(move |_| auto_create_today_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingAutoCreateToday",
+        auto_create_today,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["VimEnter"],
+        &CreateAutocmdOpts::builder()
+            .once(true)
+            .command("TimeTrackingAutoCreateToday")
+            .build(),
+    )?;
+
+    // Create command to print the summary, e.g. from `nvim --headless`
+    let print = Function::from_fn(move |_| print_summary_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingPrint",
+        print,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to open the summary in an unmanaged scratch buffer
+    let scratch = Function::from_fn(move |_| scratch_summary_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingScratch",
+        scratch,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to copy the previous day's entries (`zero` resets durations)
+    let carry_over =
+        Function::from_fn(move |args: CommandArgs| carry_over_fn(config, args.args.as_deref()));
+
+    api::create_user_command(
+        "TimeTrackingCarryOver",
+        carry_over,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .build(),
+    )?;
+
+    // Create command to add or refresh per-section subtotal lines
+    let section_totals = Function::from_fn(move |_| section_totals_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingSectionTotals",
+        section_totals,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to sort the entries by duration (`asc` for shortest first)
+    let sort_entries =
+        Function::from_fn(move |args: CommandArgs| sort_entries_fn(config, args.args.as_deref()));
+
+    api::create_user_command(
+        "TimeTrackingSort",
+        sort_entries,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::ZeroOrOne)
+            .build(),
+    )?;
+
+    // Create commands to export the week's (or a range's) entries as CSV/JSON
+    let export_csv = Function::from_fn(move |args: CommandArgs| export_csv_fn(config, &args.fargs));
+    let export_json =
+        Function::from_fn(move |args: CommandArgs| export_json_fn(config, &args.fargs));
+
+    api::create_user_command(
+        "TimeTrackingExportCsv",
+        export_csv,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::Any)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingExportJson",
+        export_json,
+        &CreateCommandOpts::builder()
+            .nargs(CommandNArgs::Any)
+            .complete(date_completion(config))
+            .build(),
+    )?;
+
+    // Create command to show or hide seconds in plugin-computed durations
+    let seconds = Function::from_fn(move |_| toggle_seconds_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingSeconds",
+        seconds,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to switch plugin headings between relative and ISO dates
+    let relative_dates = Function::from_fn(move |_| toggle_relative_dates_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingRelativeDates",
+        relative_dates,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to reopen the last closed preview as it was
+    let undo_close = Function::from_fn(move |_| undo_close_fn());
+
+    api::create_user_command(
+        "TimeTrackingUndoClose",
+        undo_close,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to maximize the preview and restore the layout after
+    let zoom = Function::from_fn(move |_| zoom_preview_fn());
+
+    api::create_user_command(
+        "TimeTrackingZoom",
+        zoom,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    // Create command to notify the running total after saving
+    let notify_on_save = Function::from_fn(move |_| notify_on_save_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingNotifyOnSave",
+        notify_on_save,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["BufWritePost"],
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingNotifyOnSave")
+            .build(),
+    )?;
+
+    // Create commands to toggle and maintain the entry signs
+    let toggle_signs = Function::from_fn(move |_| toggle_signs_fn(config));
+    let update_signs = Function::from_fn(move |_| update_signs_fn(config));
+    let clear_signs = Function::from_fn(move |_| clear_signs_fn());
+
+    api::create_user_command(
+        "TimeTrackingSigns",
+        toggle_signs,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingUpdateSigns",
+        update_signs,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingClearSigns",
+        clear_signs,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        [options::get().update_events(), vec!["BufEnter"]].concat(),
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingUpdateSigns")
+            .build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["BufLeave"],
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingClearSigns")
+            .build(),
+    )?;
+
+    // Create commands to maintain the inline durations and winbar total
+    let update_inline = Function::from_fn(move |_| update_inline_fn(config));
+    let clear_inline = Function::from_fn(move |_| clear_inline_fn());
+
+    api::create_user_command(
+        "TimeTrackingUpdateInline",
+        update_inline,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_user_command(
+        "TimeTrackingClearInline",
+        clear_inline,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        [options::get().update_events(), vec!["BufEnter"]].concat(),
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingUpdateInline")
+            .build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["BufLeave"],
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingClearInline")
+            .build(),
+    )?;
+
+    for event in options::get().unknown_update_events() {
+        log_warn!(
+            "[time-tracking] ignoring unsupported update event '{}'",
+            event
+        );
+    }
+
+    // Set up autocommands for live updates on markdown files; edits and
+    // buffer switches share one debounce, so bursts of either render once
+    api::create_autocmd(
+        [options::get().update_events(), vec!["BufEnter"]].concat(),
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingUpdateDebounced")
             .build(),
     )?;
 
     // Set up autocommand to auto-open preview after Neovim fully starts
+    let tracking_patterns: Vec<String> = std::iter::once("md")
+        .chain(options::get().formatters.keys().map(String::as_str))
+        .map(|ext| format!("*.{}", ext))
+        .collect();
     api::create_autocmd(
         vec!["VimEnter", "BufWinEnter"],
         &CreateAutocmdOpts::builder()
-            .patterns(vec!["*.md"])
+            .patterns(tracking_patterns.iter().map(String::as_str))
             .command("TimeTrackingAutoOpen")
             .build(),
     )?;
 
+    // Create command to snapshot the summary on exit; its autocommand comes
+    // before the wipe so the preview is still there to read
+    let export_on_exit = Function::from_fn(move |_| export_on_exit_fn(config));
+
+    api::create_user_command(
+        "TimeTrackingExportOnExit",
+        export_on_exit,
+        &CreateCommandOpts::builder().build(),
+    )?;
+
+    api::create_autocmd(
+        vec!["VimLeavePre"],
+        &CreateAutocmdOpts::builder()
+            .command("TimeTrackingExportOnExit")
+            .build(),
+    )?;
+
     // Set up autocommand to close preview window when quitting Neovim
     api::create_autocmd(
         vec!["VimLeavePre"],
         &CreateAutocmdOpts::builder()
-            .command("silent! bwipeout [Time Tracking Preview]")
+            .command("TimeTrackingWipePreview")
             .build(),
     )?;
 
@@ -152,6 +815,52 @@ pub fn time_tracking_with_config(config: &'static Config) -> Result<Dictionary>
         }
     });
 
-    let api = Dictionary::new();
+    // Functions callable from Lua, e.g. `require("time_tracking_nvim").week_totals()`
+    let week_totals = Function::from_fn(move |()| -> Result<_> { Ok(week_totals_fn(config)) });
+    let week_entries = Function::from_fn(move |()| -> Result<_> { Ok(week_entries_fn(config)) });
+
+    let status = Function::from_fn(move |()| status_indicator(config));
+    // Never errors: anything that goes wrong just means "not a tracking file"
+    let is_tracking_file = Function::from_fn(move |()| -> Result<bool> {
+        Ok(is_time_tracking_file(config).unwrap_or(false))
+    });
+
+    // Never errors either: any failure reads as "no total"
+    let today_total_minutes = Function::from_fn(move |()| -> Result<Option<i64>> {
+        Ok(buffer_total_minutes(config).unwrap_or(None))
+    });
+
+    // Looked up on each call, so it's never a stale handle; nil when closed
+    let preview_win = Function::from_fn(move |()| -> Result<Option<i32>> {
+        Ok(find_preview_window()
+            .unwrap_or(None)
+            .map(|win| win.handle()))
+    });
+
+    let tracking_files = Function::from_fn(move |()| -> Result<Vec<String>> {
+        Ok(files::tracking_files(config)
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect())
+    });
+
+    let api = Dictionary::from_iter([
+        ("week_totals", Object::from(week_totals)),
+        ("week_entries", Object::from(week_entries)),
+        ("status", Object::from(status)),
+        ("is_tracking_file", Object::from(is_tracking_file)),
+        ("today_total_minutes", Object::from(today_total_minutes)),
+        ("tracking_files", Object::from(tracking_files)),
+        ("preview_win", Object::from(preview_win)),
+    ]);
     Ok(api)
 }
+
+/// Completion for commands taking a date argument
+fn date_completion(config: &'static Config) -> CommandComplete {
+    CommandComplete::CustomList(Function::from_fn(
+        move |(arg_lead, _cmd_line, _cursor_pos): (String, String, usize)| -> Result<Vec<String>> {
+            Ok(files::complete_dates(config, &arg_lead))
+        },
+    ))
+}
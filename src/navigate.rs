@@ -0,0 +1,199 @@
+use super::*;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use nvim_oxi::Array;
+
+use crate::dates::{format_date, resolve_date, today};
+use crate::utils::{expand_tilde, strip_verbatim_prefix};
+
+/// Open the resolved data directory in the configured file explorer
+/// (`:edit <dir>`, so netrw or whatever replaces it)
+pub fn open_data_dir_fn(config: &'static Config) -> Result<()> {
+    let Some(configured) = config.get_data_directory() else {
+        log_warn!("[time-tracking] data_directory is not set");
+        return Ok(());
+    };
+    let Some(dir) = files::data_dir(config) else {
+        log_warn!(
+            "[time-tracking] data directory {} does not exist",
+            configured
+        );
+        return Ok(());
+    };
+
+    edit_path(&dir)
+}
+
+/// On startup, create today's daily file if it's missing and open it, when
+/// `auto_create_today` is set and Neovim was started without file arguments
+/// in or under the data directory
+pub fn auto_create_today_fn(config: &'static Config) -> Result<()> {
+    if !options::get().auto_create_today {
+        return Ok(());
+    }
+
+    let argc: i64 = api::call_function("argc", Array::new())?;
+    if argc > 0 {
+        return Ok(());
+    }
+
+    let Some(data_dir) = files::data_dir(config) else {
+        return Ok(());
+    };
+    let in_data_dir = std::env::current_dir()
+        .and_then(fs::canonicalize)
+        .map(|cwd| strip_verbatim_prefix(&cwd).starts_with(&data_dir))
+        .unwrap_or(false);
+    if !in_data_dir {
+        return Ok(());
+    }
+
+    let today = today();
+    let Some(path) = files::daily_file_path(config, today) else {
+        return Ok(());
+    };
+
+    if !path.exists() {
+        // Another instance may be creating it at the same moment; whichever
+        // gets there first wins and the other just opens it
+        if let Err(e) = files::create_new_file(&path, &new_day_content(config, today)) {
+            log_error!("[time-tracking] could not create {}: {}", path.display(), e);
+            return Ok(());
+        }
+    }
+
+    edit_path(&path)
+}
+
+/// Open the daily file for a date (today by default), creating it from the
+/// template if it's missing, and show its preview, whatever buffer this
+/// started from
+pub fn edit_day_fn(config: &'static Config, date_arg: Option<&str>) -> Result<()> {
+    let today = today();
+    let date = match date_arg.map(str::trim).filter(|arg| !arg.is_empty()) {
+        Some(arg) => match resolve_date(arg, today) {
+            Some(date) => date,
+            None => {
+                log_error!("[time-tracking] invalid date '{}'", arg);
+                return Ok(());
+            }
+        },
+        None => today,
+    };
+
+    let Some(path) = files::daily_file_path(config, date) else {
+        log_warn!("[time-tracking] data directory is not set or does not exist");
+        return Ok(());
+    };
+    if let Err(e) = files::create_new_file(&path, &new_day_content(config, date)) {
+        log_error!("[time-tracking] could not create {}: {}", path.display(), e);
+        return Ok(());
+    }
+
+    edit_path(&path)?;
+    open_preview_fn(config)
+}
+
+/// Initial content for a new daily file: the template file's contents, or
+/// just the date header when there's no readable template
+fn new_day_content(config: &Config, date: time::Date) -> String {
+    config
+        .template_file
+        .as_deref()
+        .and_then(|template| fs::read_to_string(expand_tilde(template)).ok())
+        .unwrap_or_else(|| format!("{}\n", format_date(date, &options::get().header_format)))
+}
+
+/// `vim.ui.select` over `_A.files`, labelled relative to `_A.root`, editing
+/// the chosen file. Wrapped in a function so the callbacks keep their
+/// arguments after `luaeval` returns.
+const SELECT_FILE_LUA: &str = r#"(function(a)
+  vim.ui.select(a.files, {
+    prompt = "Tracking files",
+    format_item = function(path) return path:sub(#a.root + 2) end,
+  }, function(choice)
+    if choice then vim.cmd.edit(vim.fn.fnameescape(choice)) end
+  end)
+end)(_A)"#;
+
+/// Pick a tracking file to open, newest day first, with `vim.ui.select`, or
+/// from the quickfix list if that isn't available
+pub fn files_fn(config: &'static Config) -> Result<()> {
+    let Some(data_dir) = files::data_dir(config) else {
+        log_warn!("[time-tracking] data directory is not set or does not exist");
+        return Ok(());
+    };
+    let files = files::tracking_files_by_date(config);
+    if files.is_empty() {
+        log_warn!(
+            "[time-tracking] no tracking files in {}",
+            data_dir.display()
+        );
+        return Ok(());
+    }
+
+    let paths: Array = files
+        .iter()
+        .map(|path| Object::from(path.to_string_lossy().into_owned()))
+        .collect();
+    let args = Dictionary::from_iter([
+        ("files", Object::from(paths)),
+        (
+            "root",
+            Object::from(data_dir.to_string_lossy().into_owned()),
+        ),
+    ]);
+    let selected = api::call_function::<_, Object>(
+        "luaeval",
+        Array::from_iter([Object::from(SELECT_FILE_LUA), Object::from(args)]),
+    );
+    if let Err(e) = selected {
+        log_warn!(
+            "[time-tracking] vim.ui.select failed ({}), using the quickfix list",
+            e
+        );
+        return quickfix_files(&files);
+    }
+    Ok(())
+}
+
+/// Fill the quickfix list with `files` and open it
+fn quickfix_files(files: &[PathBuf]) -> Result<()> {
+    let items: Array = files
+        .iter()
+        .map(|path| {
+            Object::from(Dictionary::from_iter([
+                (
+                    "filename",
+                    Object::from(path.to_string_lossy().into_owned()),
+                ),
+                ("lnum", Object::from(1)),
+            ]))
+        })
+        .collect();
+    let what = Dictionary::from_iter([
+        ("title", Object::from("Tracking files")),
+        ("items", Object::from(items)),
+    ]);
+    let _: i64 = api::call_function(
+        "setqflist",
+        Array::from_iter([
+            Object::from(Array::new()),
+            Object::from(" "),
+            Object::from(what),
+        ]),
+    )?;
+    api::command("copen")
+}
+
+fn edit_path(path: &Path) -> Result<()> {
+    let escaped: String = api::call_function(
+        "fnameescape",
+        Array::from_iter([path.to_string_lossy().into_owned()]),
+    )?;
+    api::command(&format!("edit {}", escaped))
+}
@@ -0,0 +1,494 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
+
+use nvim_oxi::{Array, Dictionary, api, conversion::FromObject};
+
+/// Global variable the Lua `setup()` stores the user's options in
+const OPTIONS_VAR: &str = "time_tracking_nvim";
+
+/// Events `update_events` may name
+const UPDATE_EVENTS: &[&str] = &[
+    "TextChanged",
+    "TextChangedI",
+    "InsertLeave",
+    "BufWritePost",
+    "CursorHold",
+    "CursorHoldI",
+];
+
+/// Where plugin-computed totals go in rendered content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TotalPosition {
+    /// Just under the leading heading
+    Top,
+    /// After everything else
+    #[default]
+    Bottom,
+}
+
+impl TotalPosition {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "top" => Some(Self::Top),
+            "bottom" => Some(Self::Bottom),
+            _ => None,
+        }
+    }
+}
+
+/// How many previews exist and which one a tracking buffer renders into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreviewScope {
+    /// One preview per source window
+    Window,
+    /// One preview per tabpage, for that tab's current tracking file
+    Tab,
+    /// A single preview shared by every tabpage
+    #[default]
+    Global,
+}
+
+impl PreviewScope {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "window" => Some(Self::Window),
+            "tab" => Some(Self::Tab),
+            "global" => Some(Self::Global),
+            _ => None,
+        }
+    }
+}
+
+/// How the plugin shows totals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DisplayMode {
+    /// A preview window next to the source
+    #[default]
+    Preview,
+    /// Durations at the end of entry lines and a total in the source
+    /// window's winbar, with no preview window at all
+    Inline,
+}
+
+impl DisplayMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "preview" => Some(Self::Preview),
+            "inline" => Some(Self::Inline),
+            _ => None,
+        }
+    }
+}
+
+/// Preset arrangements for the preview split, opened to the right of the
+/// source window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreviewLayout {
+    /// A third of the screen
+    #[default]
+    ThirdRight,
+    /// Half of the screen
+    Half,
+    /// Two thirds of the screen, leaving the source a third
+    TwoThirdsRight,
+}
+
+impl PreviewLayout {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "third-right" => Some(Self::ThirdRight),
+            "half" => Some(Self::Half),
+            "two-thirds-right" => Some(Self::TwoThirdsRight),
+            _ => None,
+        }
+    }
+
+    /// Preview width for a screen `columns` wide, at least 20 columns
+    pub fn width(self, columns: i64) -> i64 {
+        let width = match self {
+            Self::ThirdRight => columns / 3,
+            Self::Half => columns / 2,
+            Self::TwoThirdsRight => columns * 2 / 3,
+        };
+        width.max(20)
+    }
+}
+
+/// Plugin-side options, set through `require("time-tracking-nvim").setup({...})`.
+///
+/// These are separate from the time-tracking-cli `Config`, which is shared with
+/// the CLI and only covers data/formatting settings.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Header inserted by `TimeTrackingInsertHeader`, strftime-style
+    pub header_format: String,
+    /// Display name of the preview buffer
+    pub preview_name: String,
+    /// Preview window or inline annotations
+    pub mode: DisplayMode,
+    /// Whether there's one preview per window, per tabpage or overall
+    pub preview_scope: PreviewScope,
+    /// Preset split width; ignored with `preview_open_command`
+    pub preview_layout: PreviewLayout,
+    /// Command run verbatim to open the preview window (e.g. `topleft 40vsplit`)
+    /// instead of the default split and sizing
+    pub preview_open_command: Option<String>,
+    /// Filetype to set on the preview buffer (e.g. `markdown`), if any
+    pub preview_filetype: Option<String>,
+    /// Conceal markup in the preview when its filetype is markdown-like
+    pub preview_conceal: bool,
+    /// Dim the preview window by drawing it with `NormalNC`
+    pub preview_dim: bool,
+    /// Wrap long lines in the preview at word boundaries, with indented
+    /// continuation lines, instead of truncating them
+    pub preview_wrap: bool,
+    /// Leave the cursor in the preview after opening it with
+    /// `TimeTrackingToggle` (auto-open never moves it)
+    pub focus_preview_on_open: bool,
+    /// Start with the preview pinned (see `TimeTrackingPin`)
+    pub pin_preview: bool,
+    /// Translations for plugin-generated labels, keyed by label name
+    pub i18n: HashMap<String, String>,
+    /// Start with relative dates ("Today", "Yesterday") in plugin-generated
+    /// headings (see `TimeTrackingRelativeDates`)
+    pub relative_dates: bool,
+    /// Start with seconds shown in plugin-computed durations (see
+    /// `TimeTrackingSeconds`)
+    pub show_seconds: bool,
+    /// Line between the days of aggregated views like the week (empty for
+    /// just a blank line)
+    pub section_separator: String,
+    /// Locale used to group digits in plugin-computed numbers (e.g. `en`, `de`)
+    pub number_locale: Option<String>,
+    /// Formatter name per tracking-file extension (e.g. `{ csv = "Csv" }`).
+    /// Mapped extensions are detected as tracking files alongside `md`;
+    /// unmapped ones use the global `formatter`.
+    pub formatters: HashMap<String, String>,
+    /// Location of each day's file relative to the data directory, strftime-style
+    pub daily_file_pattern: String,
+    /// Milliseconds a listing of the data directory (for date completion and
+    /// `TimeTrackingFiles`) is reused before it's walked again (0 always walks)
+    pub rescan_interval: i64,
+    /// Don't open the preview split when the terminal is narrower than this
+    pub preview_min_total_columns: i64,
+    /// Truncate preview content past this many lines with a marker saying how
+    /// many were cut (0 renders everything)
+    pub preview_max_lines: i64,
+    /// Regex matching trailing plain hours (e.g. `Design 2.5`); the first
+    /// capture group is the hours. Unset, only `h`/`m` durations count.
+    pub decimal_hours_pattern: Option<String>,
+    /// Regex matching an entry's project tag; the first capture group is the name
+    pub project_tag_pattern: String,
+    /// List the day view's entries under their tags, with per-tag subtotals
+    pub group_by_tag: bool,
+    /// Regex matching an entry's tags for `group_by_tag`; the first capture
+    /// group (or the whole match) is the tag's name
+    pub tag_pattern: String,
+    /// Daily total in minutes above which the preview's total is highlighted
+    /// (0 disables)
+    pub daily_budget_minutes: i64,
+    /// Also warn once via `vim.notify` when the daily budget is exceeded
+    pub budget_notify: bool,
+    /// Highlight group for an over-budget total line
+    pub budget_hl: String,
+    /// Marker (e.g. `$`) flagging an entry as billable; no split when unset
+    pub billable_marker: Option<String>,
+    /// Warn in the day preview about time ranges that overlap
+    pub warn_overlaps: bool,
+    /// Lines starting with this (e.g. `//`) are notes, never entries; unset,
+    /// every line may be one
+    pub ignore_prefix: Option<String>,
+    /// Whether `setup()` got a `parse_hook`; the function itself stays on the
+    /// Lua side, which calls it in place of the built-in entry parser
+    pub parse_hook: bool,
+    /// On startup in the data directory, create and open today's daily file
+    pub auto_create_today: bool,
+    /// Preview-buffer key jumping to the entry's source line (empty disables)
+    pub goto_entry_key: String,
+    /// Preview-buffer key copying the entry's duration to the `+` register
+    /// (empty disables)
+    pub copy_entry_key: String,
+    /// Reopen the preview at the width it had when last closed
+    pub preview_remember_width: bool,
+    /// Milliseconds without edits or buffer switches before the preview
+    /// re-renders (0 renders on every event)
+    pub update_debounce: i64,
+    /// Events that re-render the preview (see [`UPDATE_EVENTS`]); empty
+    /// uses the `low_power` preset's
+    pub update_events: Vec<String>,
+    /// Battery-friendly preset: update only on `InsertLeave`/`BufWritePost`
+    /// and wait longer before auto-opening/closing
+    pub low_power: bool,
+    /// Whether computed totals go at the top or bottom of the preview
+    pub total_position: TotalPosition,
+    /// Trim leading/trailing blank lines and whitespace before formatting
+    pub trim_content: bool,
+    /// String the buffer's lines are joined with before formatting
+    pub content_join: String,
+    /// Notify the file's running total each time a tracking file is saved
+    pub notify_on_save: bool,
+    /// Path template (strftime tokens, `~`, relative to the data directory)
+    /// the preview's summary is written to when Neovim exits; off when unset
+    pub export_on_exit: Option<String>,
+    /// Shell command `TimeTrackingRunCli` runs on the current file, which
+    /// replaces `{file}` or is appended; off when unset
+    pub cli_command: Option<String>,
+    /// Scroll-bind the source window and the preview while it's open
+    pub preview_scrollbind: bool,
+    /// Start with entry signs shown (see `TimeTrackingSigns`)
+    pub signs: bool,
+    /// Sign column text for entry lines (one or two cells)
+    pub sign_text: String,
+    /// Highlight group for the entry signs
+    pub sign_hl: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            header_format: "# %Y-%m-%d".to_owned(),
+            preview_name: "[Time Tracking Preview]".to_owned(),
+            mode: DisplayMode::default(),
+            preview_scope: PreviewScope::default(),
+            preview_layout: PreviewLayout::default(),
+            preview_open_command: None,
+            preview_filetype: None,
+            preview_conceal: false,
+            preview_dim: false,
+            preview_wrap: true,
+            focus_preview_on_open: false,
+            pin_preview: false,
+            i18n: HashMap::new(),
+            relative_dates: false,
+            show_seconds: false,
+            section_separator: "---".to_owned(),
+            number_locale: None,
+            formatters: HashMap::new(),
+            daily_file_pattern: "%Y-%m-%d.md".to_owned(),
+            rescan_interval: 2000,
+            preview_min_total_columns: 80,
+            preview_max_lines: 0,
+            decimal_hours_pattern: None,
+            project_tag_pattern: r"\[([^\]]+)\]".to_owned(),
+            group_by_tag: false,
+            tag_pattern: r"#[\w-]+".to_owned(),
+            daily_budget_minutes: 0,
+            budget_notify: false,
+            budget_hl: "DiagnosticWarn".to_owned(),
+            billable_marker: None,
+            warn_overlaps: false,
+            ignore_prefix: None,
+            parse_hook: false,
+            auto_create_today: false,
+            goto_entry_key: "<CR>".to_owned(),
+            copy_entry_key: "y".to_owned(),
+            preview_remember_width: false,
+            update_debounce: 50,
+            update_events: Vec::new(),
+            low_power: false,
+            total_position: TotalPosition::default(),
+            trim_content: false,
+            content_join: "\n".to_owned(),
+            notify_on_save: false,
+            export_on_exit: None,
+            cli_command: None,
+            preview_scrollbind: false,
+            signs: false,
+            sign_text: "▎".to_owned(),
+            sign_hl: "DiagnosticSignOk".to_owned(),
+        }
+    }
+}
+
+impl Options {
+    /// Build options from the setup table, falling back to defaults for any
+    /// missing or mistyped key
+    pub fn from_dict(dict: &Dictionary) -> Self {
+        let defaults = Self::default();
+        Self {
+            header_format: lookup(dict, "header_format").unwrap_or(defaults.header_format),
+            preview_name: lookup::<String>(dict, "preview_name")
+                .filter(|name| !name.is_empty())
+                .unwrap_or(defaults.preview_name),
+            mode: lookup::<String>(dict, "mode")
+                .and_then(|value| DisplayMode::parse(&value))
+                .unwrap_or(defaults.mode),
+            preview_scope: lookup::<String>(dict, "preview_scope")
+                .and_then(|value| PreviewScope::parse(&value))
+                .unwrap_or(defaults.preview_scope),
+            preview_layout: lookup::<String>(dict, "preview_layout")
+                .and_then(|value| PreviewLayout::parse(&value))
+                .unwrap_or(defaults.preview_layout),
+            preview_open_command: lookup::<String>(dict, "preview_open_command")
+                .filter(|command| !command.trim().is_empty())
+                .or(defaults.preview_open_command),
+            preview_filetype: lookup(dict, "preview_filetype").or(defaults.preview_filetype),
+            preview_conceal: lookup(dict, "preview_conceal").unwrap_or(defaults.preview_conceal),
+            preview_dim: lookup(dict, "preview_dim").unwrap_or(defaults.preview_dim),
+            preview_wrap: lookup(dict, "preview_wrap").unwrap_or(defaults.preview_wrap),
+            focus_preview_on_open: lookup(dict, "focus_preview_on_open")
+                .unwrap_or(defaults.focus_preview_on_open),
+            pin_preview: lookup(dict, "pin_preview").unwrap_or(defaults.pin_preview),
+            i18n: lookup::<Dictionary>(dict, "i18n")
+                .map(|table| string_map(&table))
+                .unwrap_or(defaults.i18n),
+            relative_dates: lookup(dict, "relative_dates").unwrap_or(defaults.relative_dates),
+            show_seconds: lookup(dict, "show_seconds").unwrap_or(defaults.show_seconds),
+            section_separator: lookup(dict, "section_separator")
+                .unwrap_or(defaults.section_separator),
+            number_locale: lookup(dict, "number_locale").or(defaults.number_locale),
+            formatters: lookup::<Dictionary>(dict, "formatters")
+                .map(|table| string_map(&table))
+                .unwrap_or(defaults.formatters),
+            daily_file_pattern: lookup(dict, "daily_file_pattern")
+                .unwrap_or(defaults.daily_file_pattern),
+            rescan_interval: lookup(dict, "rescan_interval").unwrap_or(defaults.rescan_interval),
+            preview_min_total_columns: lookup(dict, "preview_min_total_columns")
+                .unwrap_or(defaults.preview_min_total_columns),
+            preview_max_lines: lookup(dict, "preview_max_lines")
+                .unwrap_or(defaults.preview_max_lines),
+            decimal_hours_pattern: lookup::<String>(dict, "decimal_hours_pattern")
+                .filter(|pattern| !pattern.is_empty())
+                .or(defaults.decimal_hours_pattern),
+            project_tag_pattern: lookup(dict, "project_tag_pattern")
+                .unwrap_or(defaults.project_tag_pattern),
+            group_by_tag: lookup(dict, "group_by_tag").unwrap_or(defaults.group_by_tag),
+            tag_pattern: lookup(dict, "tag_pattern").unwrap_or(defaults.tag_pattern),
+            daily_budget_minutes: lookup(dict, "daily_budget_minutes")
+                .unwrap_or(defaults.daily_budget_minutes),
+            budget_notify: lookup(dict, "budget_notify").unwrap_or(defaults.budget_notify),
+            budget_hl: lookup(dict, "budget_hl").unwrap_or(defaults.budget_hl),
+            billable_marker: lookup::<String>(dict, "billable_marker")
+                .filter(|marker| !marker.is_empty())
+                .or(defaults.billable_marker),
+            warn_overlaps: lookup(dict, "warn_overlaps").unwrap_or(defaults.warn_overlaps),
+            ignore_prefix: lookup::<String>(dict, "ignore_prefix")
+                .filter(|prefix| !prefix.trim().is_empty())
+                .or(defaults.ignore_prefix),
+            parse_hook: lookup(dict, "parse_hook").unwrap_or(defaults.parse_hook),
+            auto_create_today: lookup(dict, "auto_create_today")
+                .unwrap_or(defaults.auto_create_today),
+            goto_entry_key: lookup(dict, "goto_entry_key").unwrap_or(defaults.goto_entry_key),
+            copy_entry_key: lookup(dict, "copy_entry_key").unwrap_or(defaults.copy_entry_key),
+            preview_remember_width: lookup(dict, "preview_remember_width")
+                .unwrap_or(defaults.preview_remember_width),
+            update_debounce: lookup(dict, "update_debounce").unwrap_or(defaults.update_debounce),
+            update_events: lookup::<Array>(dict, "update_events")
+                .map(|events| string_list(&events))
+                .unwrap_or(defaults.update_events),
+            low_power: lookup(dict, "low_power").unwrap_or(defaults.low_power),
+            total_position: lookup::<String>(dict, "total_position")
+                .and_then(|value| TotalPosition::parse(&value))
+                .unwrap_or(defaults.total_position),
+            trim_content: lookup(dict, "trim_content").unwrap_or(defaults.trim_content),
+            content_join: lookup(dict, "content_join").unwrap_or(defaults.content_join),
+            notify_on_save: lookup(dict, "notify_on_save").unwrap_or(defaults.notify_on_save),
+            export_on_exit: lookup::<String>(dict, "export_on_exit")
+                .filter(|template| !template.trim().is_empty())
+                .or(defaults.export_on_exit),
+            cli_command: lookup::<String>(dict, "cli_command")
+                .filter(|command| !command.trim().is_empty())
+                .or(defaults.cli_command),
+            preview_scrollbind: lookup(dict, "preview_scrollbind")
+                .unwrap_or(defaults.preview_scrollbind),
+            signs: lookup(dict, "signs").unwrap_or(defaults.signs),
+            sign_text: lookup(dict, "sign_text").unwrap_or(defaults.sign_text),
+            sign_hl: lookup(dict, "sign_hl").unwrap_or(defaults.sign_hl),
+        }
+    }
+}
+
+impl Options {
+    /// Whether totals are shown inline rather than in a preview window
+    pub fn is_inline(&self) -> bool {
+        self.mode == DisplayMode::Inline
+    }
+
+    /// Whether files with `extension` can be tracking files: `md`, plus any
+    /// extension with a formatter mapped in `formatters`
+    pub fn is_tracking_extension(&self, extension: &str) -> bool {
+        extension == "md" || self.formatters.contains_key(extension)
+    }
+
+    /// Events that trigger a live preview update: the configured
+    /// `update_events` that are supported, or the `low_power` preset's
+    pub fn update_events(&self) -> Vec<&'static str> {
+        if !self.update_events.is_empty() {
+            return UPDATE_EVENTS
+                .iter()
+                .copied()
+                .filter(|event| self.update_events.iter().any(|e| e == event))
+                .collect();
+        }
+
+        if self.low_power {
+            vec!["InsertLeave", "BufWritePost"]
+        } else {
+            vec!["TextChanged", "TextChangedI"]
+        }
+    }
+
+    /// Configured `update_events` that aren't supported, to warn about
+    pub fn unknown_update_events(&self) -> Vec<&str> {
+        self.update_events
+            .iter()
+            .map(String::as_str)
+            .filter(|event| !UPDATE_EVENTS.contains(event))
+            .collect()
+    }
+
+    /// Delay before auto-opening the preview, letting window operations settle
+    pub fn auto_open_delay(&self) -> Duration {
+        Duration::from_millis(if self.low_power { 400 } else { 200 })
+    }
+
+    /// Delay before auto-closing the preview
+    pub fn auto_close_delay(&self) -> Duration {
+        Duration::from_millis(if self.low_power { 100 } else { 30 })
+    }
+}
+
+thread_local! {
+    static OPTIONS: RefCell<Rc<Options>> = RefCell::new(Rc::new(Options::default()));
+}
+
+/// (Re)load options from `g:time_tracking_nvim`, using defaults if it's unset
+pub fn load() {
+    let options = api::get_var::<Dictionary>(OPTIONS_VAR)
+        .map(|dict| Options::from_dict(&dict))
+        .unwrap_or_default();
+    set(options);
+}
+
+/// Replace the active options
+pub fn set(options: Options) {
+    OPTIONS.with(|o| *o.borrow_mut() = Rc::new(options));
+}
+
+/// The active options
+pub fn get() -> Rc<Options> {
+    OPTIONS.with(|o| o.borrow().clone())
+}
+
+fn lookup<T: FromObject>(dict: &Dictionary, key: &str) -> Option<T> {
+    dict.iter()
+        .find(|(k, _)| k.to_string_lossy() == key)
+        .and_then(|(_, v)| T::from_object(v.clone()).ok())
+}
+
+/// Collect the strings of a list, skipping anything else
+fn string_list(array: &Array) -> Vec<String> {
+    array
+        .iter()
+        .filter_map(|v| String::from_object(v.clone()).ok())
+        .collect()
+}
+
+/// Collect the string-valued entries of a table, skipping anything else
+fn string_map(dict: &Dictionary) -> HashMap<String, String> {
+    dict.iter()
+        .filter_map(|(k, v)| {
+            let value = String::from_object(v.clone()).ok()?;
+            Some((k.to_string_lossy().into_owned(), value))
+        })
+        .collect()
+}
@@ -1,5 +1,20 @@
 use super::*;
 
+use crate::dates::today;
+use crate::entries::{
+    Entry, EntryRange, ParsedDay, billable_seconds, format_clock, format_duration, format_precise,
+    map_entry_lines, overlapping_ranges, parse_day, parse_entry, total_minutes, total_seconds,
+};
+use crate::labels::label;
+use crate::options::PreviewScope;
+use crate::state::{ClosedPreview, View};
+use crate::utils::{buffer_lines, namespace};
+
+use nvim_oxi::api::{
+    opts::{SetExtmarkOpts, SetKeymapOpts},
+    types::Mode,
+};
+
 pub fn toggle_preview_fn(config: &'static Config) -> Result<()> {
     // Check if this is a time tracking file
     if !is_time_tracking_file(config)? {
@@ -7,32 +22,33 @@ pub fn toggle_preview_fn(config: &'static Config) -> Result<()> {
         return Ok(());
     }
 
-    // Check if preview window exists
-    let windows = api::list_wins();
-    let mut has_preview = false;
+    if find_preview_window()?.is_some() {
+        close_preview()?;
+        set_tab_wants_preview(false);
+    } else {
+        let formatted_output = render_preview_content(config)?;
+        create_or_update_preview(&formatted_output)?;
+        set_tab_wants_preview(true);
 
-    for win in windows {
-        let buf = win.get_buf()?;
-        let buf_name = buf.get_name()?;
-        if buf_name.ends_with("[Time Tracking Preview]") {
-            has_preview = true;
-            break;
+        // Only an explicit open may move the cursor; auto-open never does
+        if options::get().focus_preview_on_open {
+            focus_preview_window()?;
         }
     }
 
-    if has_preview {
-        close_preview()?;
-    } else {
-        let buffer_content = get_buffer_content()?;
-        let formatted_output = config.get_formatter().day_summary(
-            &buffer_content,
-            "",
-            config.get_prefix(),
-            config.get_suffix(),
-        );
-        create_or_update_preview(&formatted_output)?;
+    Ok(())
+}
+
+/// Open the preview for the current tracking file, or refresh it if it's
+/// already open in this tabpage
+pub fn open_preview_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
     }
 
+    let formatted_output = render_preview_content(config)?;
+    create_or_update_preview(&formatted_output)?;
+    set_tab_wants_preview(true);
     Ok(())
 }
 
@@ -42,103 +58,647 @@ pub fn update_preview_fn(config: &'static Config) -> Result<()> {
         return Ok(());
     }
 
-    // Check if preview window exists
-    let windows = api::list_wins();
-    let mut has_preview = false;
+    // A closed preview of some other file would only restore stale data
+    let current = api::get_current_buf().handle();
+    state::with(|s| {
+        if s.closed_preview
+            .as_ref()
+            .is_some_and(|c| c.source != Some(current))
+        {
+            s.closed_preview = None;
+        }
+    });
 
-    for win in windows {
-        let buf = win.get_buf()?;
-        let buf_name = buf.get_name()?;
-        if buf_name.ends_with("[Time Tracking Preview]") {
-            has_preview = true;
-            break;
+    // Keep the last snapshot while paused
+    if state::with(|s| s.paused) {
+        return Ok(());
+    }
+
+    // The global buffer is shared, so keep it current if it's shown in any
+    // tabpage; a scoped one only matters where it's shown
+    let shown = match options::get().preview_scope {
+        PreviewScope::Global => !find_preview_windows()?.is_empty(),
+        PreviewScope::Tab | PreviewScope::Window => find_preview_window()?.is_some(),
+    };
+    if shown {
+        let generation = next_render_generation();
+        let formatted_output = render_preview_content(config)?;
+        apply_render(generation, &formatted_output)?;
+    }
+
+    Ok(())
+}
+
+/// Update the preview once events stop arriving for `update_debounce`
+/// milliseconds, so a burst (typing, several `:bnext` in a row) renders just
+/// once, for whatever buffer is current by then
+pub fn debounced_update_fn(config: &'static Config) -> Result<()> {
+    // Inline mode has no preview to update. The tracking file check is cheap
+    // (the verdict is cached), and keeps idle events like `CursorHold` in
+    // other buffers from starting timers
+    if options::get().is_inline() || !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let delay = options::get().update_debounce;
+    if delay <= 0 {
+        return update_preview_fn(config);
+    }
+
+    // Any later event or render claims a newer generation, cancelling this one
+    let generation = next_render_generation();
+    let fire = Function::from_fn_once(move |_timer: i64| -> Result<()> {
+        if state::with(|s| s.render_generation == generation) {
+            update_preview_fn(config)?;
         }
+        Ok(())
+    });
+    api::call_function::<_, i64>("timer_start", (delay, fire))?;
+
+    Ok(())
+}
+
+/// Claim a generation number for a new preview render
+///
+/// Renders may complete out of order (e.g. when formatting is deferred), so
+/// each one is tagged and only the newest requested is allowed to land.
+pub fn next_render_generation() -> u64 {
+    state::with(|s| {
+        s.render_generation += 1;
+        s.render_generation
+    })
+}
+
+/// Write a render's output to the preview, unless a newer render was
+/// requested after `generation` was claimed. Returns whether it was applied.
+pub fn apply_render(generation: u64, output: &str) -> Result<bool> {
+    if state::with(|s| s.render_generation != generation) {
+        return Ok(false);
     }
+    create_or_update_preview(output)?;
+    Ok(true)
+}
 
-    if has_preview {
-        let buffer_content = get_buffer_content()?;
-        let formatted_output = config.get_formatter().day_summary(
-            &buffer_content,
-            "",
-            config.get_prefix(),
-            config.get_suffix(),
-        );
+/// Resume live updates, refreshing the preview right away
+pub fn resume_fn(config: &'static Config) -> Result<()> {
+    state::with(|s| s.paused = false);
+    update_preview_fn(config)
+}
+
+/// Move the cursor into the preview, opening it first if needed
+pub fn focus_preview_fn(config: &'static Config) -> Result<()> {
+    if find_preview_window()?.is_none() {
+        if !is_time_tracking_file(config)? {
+            return Ok(());
+        }
+        let formatted_output = render_preview_content(config)?;
         create_or_update_preview(&formatted_output)?;
+        set_tab_wants_preview(true);
+    }
+
+    focus_preview_window()
+}
+
+/// Move the cursor into this tabpage's preview window, remembering where it
+/// came from for `TimeTrackingPreviewBlur`
+fn focus_preview_window() -> Result<()> {
+    // Opening can be skipped (e.g. narrow terminal), so look again
+    let Some(win) = find_preview_window()? else {
+        return Ok(());
+    };
+    let current = api::get_current_win();
+    if current != win {
+        state::with(|s| s.focus_return = Some(current.handle()));
+        api::set_current_win(&win)?;
+    }
+
+    Ok(())
+}
+
+/// Return from the preview to the window focused before `TimeTrackingPreviewFocus`
+pub fn blur_preview_fn() -> Result<()> {
+    let Some(handle) = state::with(|s| s.focus_return.take()) else {
+        return Ok(());
+    };
+    let win = Window::from(handle);
+    if win.is_valid() {
+        api::set_current_win(&win)?;
+    }
+
+    Ok(())
+}
+
+/// Maximize the preview, or restore the window sizes from before it was
+/// maximized. Does nothing when there's no preview to zoom.
+pub fn zoom_preview_fn() -> Result<()> {
+    if let Some(layout) = state::with(|s| s.zoom_layout.take()) {
+        // Sizes constrain each other, so a second pass settles any window
+        // the first one couldn't fully restore (as `winrestcmd()` does)
+        for _ in 0..2 {
+            for (handle, width, height) in &layout {
+                let mut win = Window::from(*handle);
+                if win.is_valid() {
+                    let _ = win.set_width(*width);
+                    let _ = win.set_height(*height);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let Some(mut preview) = find_preview_window()? else {
+        return Ok(());
+    };
+
+    let mut layout = Vec::new();
+    for win in api::get_current_tabpage().list_wins()? {
+        layout.push((win.handle(), win.get_width()?, win.get_height()?));
+    }
+    state::with(|s| s.zoom_layout = Some(layout));
+
+    // Oversized values are clamped to the largest size that fits
+    let global = OptionOptsBuilder::default().build();
+    let columns = api::get_option_value::<i64>("columns", &global)?;
+    let lines = api::get_option_value::<i64>("lines", &global)?;
+    preview.set_width(columns as u32)?;
+    preview.set_height(lines as u32)?;
+
+    Ok(())
+}
+
+/// Flip the preview between the day summary and the week aggregate in place;
+/// from any other report it goes back to the day
+pub fn toggle_view_fn(config: &'static Config) -> Result<()> {
+    state::with(|s| {
+        s.view = match s.view {
+            View::Day => View::Week,
+            _ => View::Day,
+        }
+    });
+
+    if !find_preview_windows()?.is_empty() {
+        let formatted_output = render_preview_content(config)?;
+        create_or_update_preview(&formatted_output)?;
+    }
+
+    Ok(())
+}
+
+/// Build the preview content for the active view
+pub fn render_preview_content(config: &'static Config) -> Result<String> {
+    let source = api::get_current_buf().handle();
+    let (view, week_offset) = state::with(|s| {
+        s.rendered_from = Some(source);
+        (s.view, s.week_offset)
+    });
+    Ok(match view {
+        View::Day => {
+            let day = parse_buffer_day()?;
+            let output = render_buffer_summary(config, &day)?;
+
+            let mapping = map_entry_lines(&day.entries, &output);
+            state::with(|s| s.entry_lines = Some((source, mapping)));
+            check_budget(source, &day.entries, &output);
+            record_task_snapshot(source, seconds_by_task(&day.entries));
+
+            output
+        }
+        View::Week => {
+            forget_day_render();
+            render_week(config, week_offset).0
+        }
+        View::Range(start, end) => {
+            forget_day_render();
+            render_range(config, start, end)
+        }
+        View::Ytd => {
+            forget_day_render();
+            render_ytd(config, today())
+        }
+        View::Compare(first, second) => {
+            forget_day_render();
+            render_compare(config, first, second)
+        }
+        View::Projects(span) => {
+            forget_day_render();
+            render_projects_view(config, span)?
+        }
+    })
+}
+
+/// Drop what a day render left for the preview (entry lines, the budget
+/// highlight), which an aggregate view doesn't have
+fn forget_day_render() {
+    state::with(|s| {
+        s.entry_lines = None;
+        s.budget_line = None;
+    });
+}
+
+/// Namespace for the over-budget highlight in the preview
+const BUDGET_NAMESPACE: &str = "time_tracking_budget";
+
+/// Compare the day's total with `daily_budget_minutes`. Over budget, the
+/// output line showing the total is remembered for highlighting and, with
+/// `budget_notify`, a warning is shown once until the total drops back under.
+fn check_budget(source: i32, entries: &[Entry], output: &str) {
+    let options = options::get();
+    let budget = options.daily_budget_minutes;
+    let total = total_minutes(entries);
+    let over = budget > 0 && total > budget;
+
+    let newly_over = state::with(|s| {
+        if !over {
+            s.budget_line = None;
+            s.budget_notified.remove(&source);
+            return false;
+        }
+        s.budget_line = total_line(output, total);
+        s.budget_notified.insert(source)
+    });
+
+    if newly_over && options.budget_notify {
+        let _ = api::notify(
+            &format!(
+                "[time-tracking] {} logged, over the daily budget of {}",
+                format_duration(total),
+                format_duration(budget)
+            ),
+            LogLevel::Warn,
+            &Default::default(),
+        );
+    }
+}
+
+/// The output line showing the grand total: the last one with the total's
+/// duration in it, or else the last one mentioning the total
+fn total_line(output: &str, total: i64) -> Option<String> {
+    let duration = format_duration(total);
+    let total_label = label("total").to_lowercase();
+    let lines: Vec<&str> = output.lines().collect();
+    lines
+        .iter()
+        .rev()
+        .find(|line| line.contains(&duration))
+        .or_else(|| {
+            lines
+                .iter()
+                .rev()
+                .find(|line| line.to_lowercase().contains(&total_label))
+        })
+        .map(|line| line.to_string())
+}
+
+/// From the preview, jump to the source line of the entry under the cursor.
+/// Lines showing no entry (headings, totals) do nothing.
+pub fn goto_entry_fn() -> Result<()> {
+    let (row, _) = api::get_current_win().get_cursor()?;
+    let target = state::with(|s| {
+        let (source, mapping) = s.entry_lines.as_ref()?;
+        Some((*source, *mapping.get(&(row - 1))?))
+    });
+    let Some((source, line)) = target else {
+        return Ok(());
+    };
+
+    for mut win in api::get_current_tabpage().list_wins()? {
+        if win.get_buf()?.handle() == source {
+            api::set_current_win(&win)?;
+            win.set_cursor(line + 1, 0)?;
+            break;
+        }
     }
 
     Ok(())
 }
 
+/// Register `TimeTrackingCopyEntry` copies to
+const COPY_REGISTER: &str = "+";
+
+/// From the preview, copy the duration of the entry on the cursor line to the
+/// `+` register. Other lines (headings, blank lines) start the usual `y`.
+pub fn copy_entry_fn() -> Result<()> {
+    let line = api::get_current_line()?;
+    let Some(entry) = parse_entry(0, &line) else {
+        api::call_function::<_, Object>("feedkeys", ("y", "n"))?;
+        return Ok(());
+    };
+
+    let duration = format_precise(entry.duration_seconds());
+    if let Err(e) = api::call_function::<_, Object>("setreg", (COPY_REGISTER, duration.as_str())) {
+        log_error!(
+            "[time-tracking] failed to copy to the {} register: {}",
+            COPY_REGISTER,
+            e
+        );
+        return Ok(());
+    }
+    api::notify(
+        &format!(
+            "[time-tracking] copied {} ({})",
+            duration, entry.description
+        ),
+        LogLevel::Info,
+        &Default::default(),
+    )?;
+    Ok(())
+}
+
+/// Parse the current buffer's entries. Entry positions use plain newlines
+/// regardless of `content_join`, so they match the buffer's lines.
+pub fn parse_buffer_day() -> Result<ParsedDay> {
+    Ok(parse_day(&get_buffer_content_with("\n")?))
+}
+
+/// Format the current buffer, already parsed as `day`, as a day summary,
+/// with the formatter mapped to its extension (see the `formatters` option)
+pub fn render_buffer_summary(config: &'static Config, day: &ParsedDay) -> Result<String> {
+    let name = api::get_current_buf().get_name()?;
+    let extension = name.extension().map(|ext| ext.to_string_lossy());
+    let config = config_for_extension(config, extension.as_deref());
+    Ok(render_day_summary(&config, &get_buffer_content()?, day))
+}
+
+/// Format `content`, parsed as `day`, as a day summary, honoring any runtime
+/// prefix/suffix overrides, or from the `parse_hook`'s entries when there is
+/// one
+pub fn render_day_summary(config: &Config, content: &str, day: &ParsedDay) -> String {
+    let summary = if day.hooked {
+        render_hook_summary(content, &day.entries)
+    } else {
+        let (prefix, suffix) = state::with(|s| (s.prefix.clone(), s.suffix.clone()));
+        summarize_with(
+            content,
+            config,
+            prefix.as_deref().unwrap_or(config.get_prefix()),
+            suffix.as_deref().unwrap_or(config.get_suffix()),
+        )
+    };
+    let summary = if options::get().group_by_tag {
+        group_summary_by_tag(&summary, day)
+    } else {
+        summary
+    };
+
+    let summary = match billable_footer(&day.entries) {
+        Some(footer) => with_footer(&summary, &footer),
+        None => summary,
+    };
+
+    // Warnings always go last, whatever the `total_position`
+    match overlap_warnings(content) {
+        Some(warnings) => format!("{}\n\n{}", summary, warnings),
+        None => summary,
+    }
+}
+
+/// One warning line per pair of overlapping time ranges, when `warn_overlaps`
+/// is on and there are any
+pub fn overlap_warnings(content: &str) -> Option<String> {
+    if !options::get().warn_overlaps {
+        return None;
+    }
+    let overlaps = overlapping_ranges(content);
+    if overlaps.is_empty() {
+        return None;
+    }
+
+    let span = |range: &EntryRange| {
+        format!(
+            "{} {} ({}-{})",
+            label("line"),
+            range.line + 1,
+            format_clock(range.start),
+            format_clock(range.end)
+        )
+    };
+    let warnings: Vec<String> = overlaps
+        .iter()
+        .map(|(a, b)| format!("⚠ {}: {}, {}", label("overlap"), span(a), span(b)))
+        .collect();
+    Some(warnings.join("\n"))
+}
+
+/// A day summary of entries from the `parse_hook`, in place of the
+/// formatter's: the content's first heading, one line per entry and the total
+pub fn render_hook_summary(content: &str, entries: &[Entry]) -> String {
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with('#'))
+        .map(|heading| vec![heading.to_owned(), String::new()])
+        .unwrap_or_default();
+    lines.extend(entries.iter().map(|entry| {
+        format!(
+            "- {}: {}",
+            entry.description,
+            format_precise(entry.duration_seconds())
+        )
+    }));
+
+    with_footer(
+        &lines.join("\n"),
+        &format!(
+            "{}: {}",
+            label("total"),
+            format_precise(total_seconds(entries))
+        ),
+    )
+}
+
+/// Billable/non-billable subtotals and the grand total, when a
+/// `billable_marker` is configured
+pub fn billable_footer(entries: &[Entry]) -> Option<String> {
+    let marker = options::get().billable_marker.clone()?;
+    let (billable, non_billable) = billable_seconds(entries, &marker);
+    Some(format!(
+        "{}: {}\n{}: {}\n{}: {}",
+        label("billable"),
+        format_precise(billable),
+        label("non_billable"),
+        format_precise(non_billable),
+        label("total"),
+        format_precise(billable + non_billable)
+    ))
+}
+
+/// Set (or with `None`/empty, reset) the runtime prefix and re-render the preview
+pub fn set_prefix_fn(config: &'static Config, prefix: Option<&str>) -> Result<()> {
+    let prefix = prefix.filter(|p| !p.is_empty()).map(str::to_owned);
+    state::with(|s| s.prefix = prefix);
+    update_preview_fn(config)
+}
+
+/// Set (or with `None`/empty, reset) the runtime suffix and re-render the preview
+pub fn set_suffix_fn(config: &'static Config, suffix: Option<&str>) -> Result<()> {
+    let suffix = suffix.filter(|s| !s.is_empty()).map(str::to_owned);
+    state::with(|s| s.suffix = suffix);
+    update_preview_fn(config)
+}
+
+/// Keep the first `max` lines (all of them for 0), replacing the rest with a
+/// marker saying how many were cut
+pub fn truncate_lines(mut lines: Vec<String>, max: usize) -> Vec<String> {
+    if max == 0 || lines.len() <= max {
+        return lines;
+    }
+
+    let hidden = lines.len() - max;
+    lines.truncate(max);
+    lines.push(format!("… ({} more lines)", hidden));
+    lines
+}
+
 /// Create or update the preview window with formatted time tracking data
 pub fn create_or_update_preview(output: &str) -> Result<()> {
+    // Inline mode never opens a preview window, for any view
+    if options::get().is_inline() {
+        return Ok(());
+    }
+
     // Bail if Neovim has no windows yet (during early startup churn)
     if api::list_wins().len() == 0 {
         return Ok(());
     }
 
-    // Find an existing preview buffer
-    let mut preview: Option<Buffer> = None;
-    for b in api::list_bufs() {
-        if b.get_name()?.ends_with("[Time Tracking Preview]") {
-            preview = Some(b);
-            break;
-        }
+    // While a macro is recorded or replayed, never change the window layout
+    // under it: only refresh a preview that already exists
+    let in_macro = macro_active();
+    if in_macro && find_preview_buffer()?.is_none() {
+        return Ok(());
     }
 
     // Create a scratch buffer if missing
-    let mut buf: Buffer = match preview {
+    let mut buf: Buffer = match find_preview_buffer()? {
         Some(b) => b,
         None => {
             let mut b = api::create_buf(false, true)?; // listed=false, scratch=true
-            b.set_name("[Time Tracking Preview]")?;
+            // Scoped previews need distinct names, so they carry their key
+            let key = preview_key()?;
+            let name = match key {
+                0 => options::get().preview_name.clone(),
+                key => format!("{} ({})", options::get().preview_name, key),
+            };
+            b.set_name(&name)?;
+            b.set_var(PREVIEW_VAR, true)?;
+            b.set_var(PREVIEW_KEY_VAR, key)?;
 
-            // Keep it unlisted and non-modifiable by default (DO NOT set 'readonly')
+            // Keep it unlisted and non-modifiable by default (DO NOT set 'readonly'
+            // here; only `noedit` opts into that, see `harden_preview_buffer`)
             let bopts = OptionOptsBuilder::default().buffer(b.clone()).build();
             api::set_option_value("buflisted", false, &bopts)?;
             api::set_option_value("modifiable", false, &bopts)?;
             api::set_option_value("bufhidden", "wipe", &bopts)?;
             api::set_option_value("swapfile", false, &bopts)?;
+            if let Some(filetype) = &options::get().preview_filetype {
+                api::set_option_value("filetype", filetype.as_str(), &bopts)?;
+            }
+            let goto_key = options::get().goto_entry_key.clone();
+            if !goto_key.is_empty() {
+                b.set_keymap(
+                    Mode::Normal,
+                    &goto_key,
+                    "<Cmd>TimeTrackingGotoEntry<CR>",
+                    &SetKeymapOpts::builder().noremap(true).silent(true).build(),
+                )?;
+            }
+            let copy_key = options::get().copy_entry_key.clone();
+            if !copy_key.is_empty() {
+                b.set_keymap(
+                    Mode::Normal,
+                    &copy_key,
+                    "<Cmd>TimeTrackingCopyEntry<CR>",
+                    &SetKeymapOpts::builder().noremap(true).silent(true).build(),
+                )?;
+            }
+            // With the CLI's `noedit` set, lock it down further after all
+            if state::with(|s| s.noedit) {
+                harden_preview_buffer(&mut b)?;
+            }
             b
         }
     };
 
     // Update buffer contents safely by toggling only 'modifiable'
+    let max_lines = usize::try_from(options::get().preview_max_lines).unwrap_or(0);
     {
         let bopts = OptionOptsBuilder::default().buffer(buf.clone()).build();
         api::set_option_value("modifiable", true, &bopts)?;
         let lines: Vec<String> = output.lines().map(|s| s.to_string()).collect();
-        buf.set_lines(0..buf.line_count()?, false, lines)?;
+        buf.set_lines(
+            0..buf.line_count()?,
+            false,
+            truncate_lines(lines, max_lines),
+        )?;
         api::set_option_value("modifiable", false, &bopts)?;
     }
 
-    // Is the preview buffer already shown?
-    let mut is_open = false;
-    for w in api::list_wins() {
-        if w.get_buf()? == buf {
-            is_open = true;
-            break;
-        }
+    // Highlight the total if the render of this output found it over budget
+    let ns = namespace(BUDGET_NAMESPACE);
+    buf.clear_namespace(ns, ..)?;
+    let over_budget = state::with(|s| s.budget_line.take()).and_then(|total_line| {
+        let lines: Vec<&str> = output.lines().collect();
+        lines.iter().rposition(|line| *line == total_line)
+    });
+    // A total cut off by `preview_max_lines` has nothing left to highlight
+    if let Some(line) = over_budget.filter(|line| max_lines == 0 || *line < max_lines) {
+        let opts = SetExtmarkOpts::builder()
+            .line_hl_group(options::get().budget_hl.as_str())
+            .build();
+        buf.set_extmark(ns, line, 0, &opts)?;
     }
+    emit_preview_event(PREVIEW_UPDATED);
+
+    // If the preview buffer isn't shown yet, create a vertical split and attach
+    // it. Never split from a window that already holds the preview (e.g. after
+    // TimeTrackingPreviewFocus), which would just duplicate it.
+    let current_is_preview = has_preview_var(&api::get_current_buf());
+    if !current_is_preview && find_preview_window()?.is_none() && !in_macro {
+        // On very narrow terminals a split leaves both panes unusable
+        let min_columns = options::get().preview_min_total_columns;
+        let total_cols =
+            api::get_option_value::<i64>("columns", &OptionOptsBuilder::default().build())
+                .unwrap_or(i64::MAX);
+        if total_cols < min_columns {
+            // Only say so once per session; auto-open would otherwise repeat it
+            if !state::with(|s| std::mem::replace(&mut s.narrow_warned, true)) {
+                let _ = api::notify(
+                    &format!(
+                        "[time-tracking] terminal too narrow for the preview ({} < {} columns)",
+                        total_cols, min_columns
+                    ),
+                    LogLevel::Info,
+                    &Default::default(),
+                );
+            }
+            return Ok(());
+        }
+
+        // A user-supplied open command replaces our split and sizing entirely
+        let open_command = options::get().preview_open_command.clone();
+        let source_win = api::get_current_win();
 
-    // If not, create a vertical split and attach the preview buffer to it
-    if !is_open {
         // Use a plain command for portability; it’s fine here.
-        if let Err(e) = api::command("rightbelow vsplit") {
-            let msg = e.to_string();
-            if msg.contains("E242") || msg.contains("Can't split a window while closing another") {
-                // Window operation in progress; skip silently
-                return Ok(());
+        if let Err(e) = api::command(open_command.as_deref().unwrap_or("rightbelow vsplit")) {
+            if !is_window_busy(&e) {
+                log_error!("[time-tracking] failed to split: {}", e);
             }
-            eprintln!("[time-tracking] failed to split: {}", msg);
             return Ok(());
         }
 
         // Current window is the new split
         let mut win: Window = api::get_current_win();
+        if win == source_win {
+            // The open command didn't create a window; don't take over the source
+            log_error!("[time-tracking] preview_open_command did not open a new window");
+            return Ok(());
+        }
 
         // Attach our preview buffer
         if let Err(e) = win.set_buf(&buf) {
-            eprintln!("[time-tracking] failed to set preview buffer: {}", e);
+            if !is_window_busy(&e) {
+                log_error!("[time-tracking] failed to set preview buffer: {}", e);
+            }
             let _ = win.close(false);
             return Ok(());
         }
@@ -147,32 +707,429 @@ pub fn create_or_update_preview(output: &str) -> Result<()> {
         let wopts = OptionOptsBuilder::default().win(win.clone()).build();
         let _ = api::set_option_value("winfixwidth", true, &wopts);
 
-        // Make it ~1/3 of the screen (columns is global; default opts OK)
-        if let Ok(total_cols) =
-            api::get_option_value::<i64>("columns", &OptionOptsBuilder::default().build())
+        // Conceal markdown markup for a cleaner summary, if requested
+        if options::get().preview_conceal && is_markdown_like(&buf) {
+            let _ = api::set_option_value("conceallevel", 2, &wopts);
+            let _ = api::set_option_value("concealcursor", "nc", &wopts);
+        }
+
+        // Dim the summary like an unfocused window; the option is local to
+        // this window, so it goes away with it
+        if options::get().preview_dim {
+            let _ = api::set_option_value("winhighlight", PREVIEW_DIM_HIGHLIGHT, &wopts);
+        }
+
+        // Wrap long descriptions in the narrow split rather than cutting
+        // them off, unless truncation is preferred
+        let wrap = options::get().preview_wrap;
+        let _ = api::set_option_value("wrap", wrap, &wopts);
+        if wrap {
+            let _ = api::set_option_value("linebreak", true, &wopts);
+            let _ = api::set_option_value("breakindent", true, &wopts);
+        }
+
+        // Only a freshly opened split (or a layout change) gets a width;
+        // content updates never resize it. A user-supplied open command
+        // sizes the window itself.
+        let sized = match open_command.is_none().then(preview_width).flatten() {
+            Some(width) => win.set_width(width),
+            None => Ok(()),
+        };
+        if let Some(e) = sized.err().filter(|e| !is_window_busy(e)) {
+            log_error!("[time-tracking] failed to size the preview: {}", e);
+        }
+
+        // Return to the previous window
+        if let Some(e) = api::command("wincmd p")
+            .err()
+            .filter(|e| !is_window_busy(e))
         {
-            let width = (total_cols / 3).max(20) as u32;
+            log_error!("[time-tracking] failed to leave the preview: {}", e);
+        }
+
+        if options::get().preview_scrollbind {
+            bind_scroll(&win);
+        }
+
+        emit_preview_event(PREVIEW_OPENED);
+    }
+
+    Ok(())
+}
+
+/// Whether `err` says the window layout can't change right now (a window is
+/// being closed, or an autocommand locks the layout). The open path bails
+/// quietly on these; they resolve once the layout churn settles.
+pub fn is_window_busy(err: &impl std::fmt::Display) -> bool {
+    const BUSY: &[&str] = &[
+        "E242",
+        "E1159",
+        "E1312",
+        "E565",
+        "Can't split a window while closing another",
+    ];
+    let msg = err.to_string();
+    BUSY.iter().any(|marker| msg.contains(marker))
+}
+
+/// Width the preview split gets: the remembered width, or the share of the
+/// screen the `preview_layout` gives it
+fn preview_width() -> Option<u32> {
+    match state::with(|s| s.preview_width) {
+        Some(width) => Some(width),
+        // columns is global; default opts OK
+        None => api::get_option_value::<i64>("columns", &OptionOptsBuilder::default().build())
+            .ok()
+            .map(|total_cols| options::get().preview_layout.width(total_cols) as u32),
+    }
+}
+
+/// Reapply the preview's width after windows were opened or closed.
+///
+/// The resize is scheduled, once per burst of layout events, so it runs on
+/// the settled layout; in particular after `TimeTrackingMaybeCloseIfInvisible`
+/// has had its chance to close the preview, instead of re-entering it.
+pub fn resize_preview_fn() -> Result<()> {
+    if options::get().preview_open_command.is_some() {
+        return Ok(());
+    }
+    if state::with(|s| std::mem::replace(&mut s.resize_pending, true)) {
+        return Ok(());
+    }
+
+    schedule(|_| {
+        state::with(|s| s.resize_pending = false);
+        if let (Ok(Some(mut win)), Some(width)) = (find_preview_window(), preview_width()) {
             let _ = win.set_width(width);
         }
+    });
+    Ok(())
+}
 
-        // Return to the previous window
-        let _ = api::command("wincmd p");
+/// `winhighlight` mapping applied to the preview window with `preview_dim`
+pub const PREVIEW_DIM_HIGHLIGHT: &str = "Normal:NormalNC";
+
+/// Short indicator for a statusline/tabline: `●TT` in a tracking file with the
+/// preview open, `○TT` in a tracking file without it, empty otherwise.
+///
+/// Statuslines redraw constantly, so this relies on the cached detection
+/// verdict and only looks at the current tabpage's windows.
+pub fn status_indicator(config: &Config) -> Result<String> {
+    if !is_time_tracking_file(config)? {
+        return Ok(String::new());
+    }
+
+    Ok(if find_preview_window()?.is_some() {
+        "●TT".to_owned()
+    } else {
+        "○TT".to_owned()
+    })
+}
+
+/// Keys that would start an edit in the preview, mapped to no-ops under `noedit`
+const EDIT_KEYS: &[&str] = &[
+    "i", "I", "a", "A", "o", "O", "c", "C", "s", "S", "r", "R", "x", "X", "d", "D", "p", "P", "u",
+    "<C-r>", ".",
+];
+
+/// Harden the preview buffer against edits beyond `modifiable=false`: mark it
+/// `readonly`, turn common edit keys into no-ops and refuse writes
+fn harden_preview_buffer(buf: &mut Buffer) -> Result<()> {
+    let bopts = OptionOptsBuilder::default().buffer(buf.clone()).build();
+    api::set_option_value("readonly", true, &bopts)?;
+
+    let nop = SetKeymapOpts::builder().noremap(true).nowait(true).build();
+    for key in EDIT_KEYS {
+        buf.set_keymap(Mode::Normal, key, "<Nop>", &nop)?;
     }
 
+    api::create_autocmd(
+        vec!["BufWriteCmd"],
+        &CreateAutocmdOpts::builder()
+            .buffer(buf.clone())
+            .command(
+                "echohl ErrorMsg | echo '[time-tracking] the preview is read-only' | echohl None",
+            )
+            .build(),
+    )?;
+
+    Ok(())
+}
+
+/// Open the current day summary in a plain scratch buffer in the current
+/// window. Unlike the preview it isn't managed: the auto open/close logic
+/// ignores it and it's wiped once the user closes it.
+pub fn scratch_summary_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let output = render_buffer_summary(config, &parse_buffer_day()?)?;
+
+    let mut buf = api::create_buf(false, true)?; // listed=false, scratch=true
+    let lines: Vec<String> = output.lines().map(|s| s.to_string()).collect();
+    buf.set_lines(0..buf.line_count()?, false, lines)?;
+
+    let bopts = OptionOptsBuilder::default().buffer(buf.clone()).build();
+    api::set_option_value("modifiable", false, &bopts)?;
+    api::set_option_value("bufhidden", "wipe", &bopts)?;
+
+    api::set_current_buf(&buf)?;
+
     Ok(())
 }
 
-/// Close the preview window if it exists
+/// Scroll-bind the current (source) window with the freshly opened preview
+///
+/// Scrollbind keeps the relative scroll offset, so with differing line counts
+/// the two stay roughly rather than exactly aligned; the global `scrollopt`
+/// is left to the user.
+fn bind_scroll(preview: &Window) {
+    let source = api::get_current_win();
+    for win in [preview, &source] {
+        let wopts = OptionOptsBuilder::default().win(win.clone()).build();
+        let _ = api::set_option_value("scrollbind", true, &wopts);
+    }
+    // Start both windows from the same relative position
+    let _ = api::command("syncbind");
+
+    let tab = api::get_current_tabpage().handle();
+    state::with(|s| s.scrollbound.insert(tab, source.handle()));
+}
+
+/// Clear the scrollbind set by [`bind_scroll`] on the source window of `tab`,
+/// or of every tabpage when `None`
+fn release_scrollbind(tab: Option<i32>) {
+    let sources: Vec<i32> = state::with(|s| match tab {
+        Some(tab) => s.scrollbound.remove(&tab).into_iter().collect(),
+        None => s.scrollbound.drain().map(|(_, win)| win).collect(),
+    });
+
+    for handle in sources {
+        let win = Window::from(handle);
+        if win.is_valid() {
+            let wopts = OptionOptsBuilder::default().win(win).build();
+            let _ = api::set_option_value("scrollbind", false, &wopts);
+        }
+    }
+}
+
+/// Print the current day summary without touching any windows: to stdout when
+/// running headless (for pipelines), otherwise to the message area
+pub fn print_summary_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        log_warn!("[time-tracking] not a time tracking file");
+        return Ok(());
+    }
+
+    let output = render_buffer_summary(config, &parse_buffer_day()?)?;
+
+    if api::list_uis().next().is_none() {
+        use std::io::Write;
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout, "{}", output);
+        let _ = stdout.flush();
+        return Ok(());
+    }
+
+    api::echo([(output.as_str(), None::<&str>)], true, &Default::default())
+}
+
+/// Total minutes of the current buffer's entries, or `None` outside tracking
+/// files
+pub fn buffer_total_minutes(config: &'static Config) -> Result<Option<i64>> {
+    Ok(buffer_entries(config)?.map(|entries| total_minutes(&entries)))
+}
+
+/// The current buffer's entries, or `None` outside tracking files
+fn buffer_entries(config: &'static Config) -> Result<Option<Vec<Entry>>> {
+    if !is_time_tracking_file(config)? {
+        return Ok(None);
+    }
+    Ok(Some(parse_buffer_day()?.entries))
+}
+
+/// One-line running total for the current tracking buffer, e.g. `Today: 6h30m`,
+/// or `None` outside tracking files
+pub fn save_total_message(config: &'static Config) -> Result<Option<String>> {
+    Ok(buffer_entries(config)?.map(|entries| total_message(config, &entries)))
+}
+
+/// [`save_total_message`] for the current buffer's already parsed `entries`
+pub fn total_message(config: &'static Config, entries: &[Entry]) -> String {
+    // Older files get the neutral label so the message never claims "today"
+    let name = if current_buffer_date(config) == Some(today()) {
+        label("today")
+    } else {
+        label("total")
+    };
+    format!("{}: {}", name, format_precise(total_seconds(entries)))
+}
+
+/// Turn seconds in plugin-computed durations on or off
+pub fn toggle_seconds_fn(config: &'static Config) -> Result<()> {
+    state::with(|s| s.show_seconds = !s.show_seconds);
+    update_preview_fn(config)?;
+    update_inline_fn(config)
+}
+
+/// Notify the running total after a tracking file is written, if
+/// `notify_on_save` is on
+pub fn notify_on_save_fn(config: &'static Config) -> Result<()> {
+    if !options::get().notify_on_save {
+        return Ok(());
+    }
+
+    if let Some(message) = save_total_message(config)? {
+        api::notify(&message, LogLevel::Info, &Default::default())?;
+    }
+    Ok(())
+}
+
+/// Whether the buffer's filetype is markdown or a markdown flavor (e.g. `markdown.pandoc`)
+fn is_markdown_like(buf: &Buffer) -> bool {
+    let bopts = OptionOptsBuilder::default().buffer(buf.clone()).build();
+    api::get_option_value::<String>("filetype", &bopts)
+        .map(|ft| {
+            ft.split('.')
+                .any(|part| matches!(part, "markdown" | "pandoc" | "rmd" | "quarto" | "vimwiki"))
+        })
+        .unwrap_or(false)
+}
+
+/// Wipe every buffer this instance created as a preview
+///
+/// Matches on the buffer-local marker rather than the name, so a user's own
+/// buffer that happens to be named like the preview is never touched.
+pub fn wipe_preview_buffers() -> Result<()> {
+    for buf in api::list_bufs() {
+        if has_preview_var(&buf) {
+            buf.delete(&BufDeleteOpts::builder().force(true).build())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Close the current tabpage's preview window if it exists
 pub fn close_preview() -> Result<()> {
-    let windows = api::list_wins();
+    if let Some(win) = find_preview_window()? {
+        close_preview_window(win, Some(api::get_current_tabpage().handle()))?;
+        emit_preview_event(PREVIEW_CLOSED);
+    }
+
+    Ok(())
+}
+
+/// Close a preview window, remembering its width for the next open when
+/// `preview_remember_width` is set, and undo the scrollbind of `tab` (or of
+/// every tabpage when `None`)
+fn close_preview_window(win: Window, tab: Option<i32>) -> Result<()> {
+    let buf = win.get_buf()?;
+    let content = buffer_lines(&buf)?;
+    state::with(|s| {
+        s.closed_preview = Some(ClosedPreview {
+            source: s.rendered_from,
+            content: content.join("\n"),
+            view: s.view,
+            week_offset: s.week_offset,
+            paused: s.paused,
+        })
+    });
+
+    if options::get().preview_remember_width {
+        if let Ok(width) = win.get_width() {
+            state::with(|s| s.preview_width = Some(width));
+        }
+    }
+
+    win.close(false)?;
+    release_scrollbind(tab);
+
+    Ok(())
+}
+
+/// Reopen the last closed preview with the content and view it had, without
+/// re-rendering
+pub fn undo_close_fn() -> Result<()> {
+    if find_preview_window()?.is_some() {
+        return Ok(());
+    }
+    let Some(closed) = state::with(|s| s.closed_preview.take()) else {
+        return Ok(());
+    };
+
+    state::with(|s| {
+        s.view = closed.view;
+        s.week_offset = closed.week_offset;
+        s.paused = closed.paused;
+        s.rendered_from = closed.source;
+    });
+    create_or_update_preview(&closed.content)?;
+    set_tab_wants_preview(true);
+
+    Ok(())
+}
+
+/// Close every preview window, across all tabpages
+pub fn close_all_previews() -> Result<()> {
+    let windows = find_preview_windows()?;
+    if windows.is_empty() {
+        return Ok(());
+    }
 
     for win in windows {
-        let buf = win.get_buf()?;
-        let buf_name = buf.get_name()?;
-        if buf_name.ends_with("[Time Tracking Preview]") {
-            win.close(false)?;
-            break;
+        close_preview_window(win, None)?;
+    }
+    emit_preview_event(PREVIEW_CLOSED);
+
+    Ok(())
+}
+
+/// `User` autocommand patterns fired as the preview changes, e.g.
+/// `autocmd User TimeTrackingPreviewOpened ...`
+pub const PREVIEW_OPENED: &str = "TimeTrackingPreviewOpened";
+pub const PREVIEW_CLOSED: &str = "TimeTrackingPreviewClosed";
+pub const PREVIEW_UPDATED: &str = "TimeTrackingPreviewUpdated";
+
+/// Fire a `User` autocommand so users can hook into preview changes. Errors
+/// from user handlers are reported but never abort the preview operation.
+fn emit_preview_event(pattern: &str) {
+    let opts = ExecAutocmdsOpts::builder()
+        .patterns(pattern)
+        .modeline(false)
+        .build();
+    if let Err(e) = api::exec_autocmds(["User"], &opts) {
+        log_error!("[time-tracking] {} handler failed: {}", pattern, e);
+    }
+}
+
+/// Record whether the user wants the preview open in the current tabpage
+pub fn set_tab_wants_preview(wanted: bool) {
+    let tab = api::get_current_tabpage().handle();
+    state::with(|s| s.tab_wants_preview.insert(tab, wanted));
+}
+
+/// On entering a tabpage, reopen or close its preview to match what the user
+/// last chose there. Tabpages with no recorded choice are left alone.
+pub fn restore_tab_preview(config: &'static Config) -> Result<()> {
+    let tab = api::get_current_tabpage();
+    let wanted = state::with(|s| s.tab_wants_preview.get(&tab.handle()).copied());
+
+    match wanted {
+        Some(true) => {
+            if is_time_tracking_file(config)? && find_preview_window_in(&tab)?.is_none() {
+                let formatted_output = render_preview_content(config)?;
+                create_or_update_preview(&formatted_output)?;
+            }
+        }
+        Some(false) => {
+            if let Some(win) = find_preview_window_in(&tab)? {
+                close_preview_window(win, Some(tab.handle()))?;
+                emit_preview_event(PREVIEW_CLOSED);
+            }
         }
+        None => {}
     }
 
     Ok(())
@@ -191,8 +1148,13 @@ pub fn auto_open_preview(config: &'static Config) -> Result<()> {
 }
 
 pub fn auto_open_preview_impl(config: &'static Config) -> Result<()> {
+    // Inline mode has no preview to open
+    if options::get().is_inline() {
+        return Ok(());
+    }
+
     // Add a small delay to avoid race conditions with window operations
-    std::thread::sleep(std::time::Duration::from_millis(200));
+    std::thread::sleep(options::get().auto_open_delay());
 
     // Check if this is a time tracking file
     let is_tracking = is_time_tracking_file(config)?;
@@ -201,34 +1163,40 @@ pub fn auto_open_preview_impl(config: &'static Config) -> Result<()> {
         return Ok(());
     }
 
-    // Check if preview window already exists
-    let windows = api::list_wins();
-    let mut has_preview = false;
-
-    for win in windows {
-        let buf = win.get_buf()?;
-        let buf_name = buf.get_name()?;
-        if buf_name.ends_with("[Time Tracking Preview]") {
-            has_preview = true;
-            break;
-        }
+    // With several files on the command line, events can fire for argument
+    // list buffers that aren't on screen; only the active one gets a preview
+    if !current_buffer_displayed()? {
+        log_info!("[TimeTracking] Auto-open: Buffer isn't displayed");
+        return Ok(());
     }
 
-    // Only open if preview doesn't already exist
-    if !has_preview {
-        let buffer_content = get_buffer_content()?;
-        let formatted_output = config.get_formatter().day_summary(
-            &buffer_content,
-            "",
-            config.get_prefix(),
-            config.get_suffix(),
-        );
-        create_or_update_preview(&formatted_output)?;
+    // Re-check right before reading the buffer: another auto-open may have
+    // created the preview while we were sleeping, and the full-buffer read
+    // plus formatting is the expensive part.
+    if find_preview_window()?.is_some() {
+        log_info!("[TimeTracking] Auto-open: Preview already exists");
+        return Ok(());
     }
 
+    let formatted_output = render_preview_content(config)?;
+    create_or_update_preview(&formatted_output)?;
+    set_tab_wants_preview(true);
+
     Ok(())
 }
 
+/// Whether the current buffer is shown in the current window of this
+/// tabpage's layout, rather than made current behind the scenes (`:argdo`,
+/// `:doautoall`, `nvim_buf_call`)
+fn current_buffer_displayed() -> Result<bool> {
+    let win = api::get_current_win();
+    let kind: String = api::call_function("win_gettype", nvim_oxi::Array::new())?;
+    if kind == "autocmd" || win.get_buf()? != api::get_current_buf() {
+        return Ok(false);
+    }
+    Ok(api::get_current_tabpage().list_wins()?.any(|w| w == win))
+}
+
 /// Auto-close preview window if we're not in a time tracking file
 pub fn auto_close_preview(config: &'static Config) -> Result<()> {
     // Add error handling wrapper to prevent panics
@@ -241,22 +1209,38 @@ pub fn auto_close_preview(config: &'static Config) -> Result<()> {
     }
 }
 
-pub fn auto_close_preview_impl(_config: &'static Config) -> Result<()> {
+pub fn auto_close_preview_impl(config: &'static Config) -> Result<()> {
+    // A pinned preview is only closed explicitly
+    if state::with(|s| s.pinned) {
+        return Ok(());
+    }
+
     // Add a small delay to avoid race conditions with window operations
-    std::thread::sleep(std::time::Duration::from_millis(30));
+    std::thread::sleep(options::get().auto_close_delay());
 
-    // Always close the preview when BufLeave is triggered for a markdown file
-    // The autocommand pattern ensures we only get called for .md files
-    // Check if preview window exists and close it
-    let windows = api::list_wins();
-    for win in windows {
-        let buf = win.get_buf()?;
-        let buf_name = buf.get_name()?;
-        if buf_name.ends_with("[Time Tracking Preview]") {
-            log_info!("Auto-closing preview (leaving markdown file)\n");
-            win.close(false)?;
-            break;
+    // On BufLeave the buffer being entered isn't current yet, so decide once
+    // the switch is done: moving on to another tracking file keeps the
+    // preview open instead of flashing it closed and open again
+    schedule(move |_| {
+        if let Err(e) = close_unless_tracking(config) {
+            log_error!("Auto-close failed: {}", e);
         }
+    });
+
+    Ok(())
+}
+
+/// Close this tabpage's preview unless the current buffer is a tracking file
+/// (or the preview itself)
+fn close_unless_tracking(config: &'static Config) -> Result<()> {
+    if has_preview_var(&api::get_current_buf()) || is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    if let Some(win) = find_preview_window()? {
+        log_info!("Auto-closing preview (left the tracking files)\n");
+        close_preview_window(win, Some(api::get_current_tabpage().handle()))?;
+        emit_preview_event(PREVIEW_CLOSED);
     }
 
     Ok(())
@@ -0,0 +1,173 @@
+use super::*;
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use time::Date;
+
+use crate::aggregate::{heading_date, load_day};
+use crate::dates::{days_between, format_date, resolve_date, today};
+use crate::entries::{format_precise, parse_day};
+use crate::labels::label;
+use crate::state::View;
+
+/// Total seconds per project tag, with untagged entries under `None`.
+///
+/// The project is the first capture group of `pattern` (or the whole match if
+/// it has no groups) on the entry's line; only the first tag counts.
+pub fn seconds_by_project(content: &str, pattern: &Regex) -> BTreeMap<Option<String>, i64> {
+    let day = parse_day(content);
+    let mut projects = BTreeMap::new();
+    for entry in &day.entries {
+        let project = line_project(&day.lines[entry.line], pattern);
+        *projects.entry(project).or_insert(0) += entry.duration_seconds();
+    }
+    projects
+}
+
+/// The project tagged on an entry's line, per [`seconds_by_project`]
+pub fn line_project(line: &str, pattern: &Regex) -> Option<String> {
+    pattern.captures(line).and_then(|caps| {
+        caps.get(1)
+            .or_else(|| caps.get(0))
+            .map(|m| m.as_str().trim().to_owned())
+    })
+}
+
+/// Render per-project totals, largest first, with the untagged bucket last
+pub fn render_projects(title: &str, projects: &BTreeMap<Option<String>, i64>) -> String {
+    let mut rows: Vec<(&Option<String>, &i64)> = projects.iter().collect();
+    rows.sort_by(|(a_name, a_seconds), (b_name, b_seconds)| {
+        a_name
+            .is_none()
+            .cmp(&b_name.is_none())
+            .then(b_seconds.cmp(a_seconds))
+    });
+
+    let mut out = format!("# {}\n", title);
+    if rows.is_empty() {
+        out.push_str(&format!("\n_{}_", label("no_data")));
+        return out;
+    }
+
+    for (name, seconds) in &rows {
+        let name = match name {
+            Some(name) => name.clone(),
+            None => label("untagged"),
+        };
+        out.push_str(&format!("\n- {}: {}", name, format_precise(**seconds)));
+    }
+    let total: i64 = projects.values().sum();
+    with_footer(
+        &out,
+        &format!("{}: {}", label("total"), format_precise(total)),
+    )
+}
+
+/// Show per-project totals in the preview for the current buffer (no
+/// arguments), one date, or an inclusive range of dates, switching it to that
+/// view so live updates keep showing it
+pub fn projects_fn(config: &'static Config, args: &[String]) -> Result<()> {
+    if project_pattern().is_none() {
+        return Ok(());
+    }
+
+    let today = today();
+    let resolve = |arg: &String| {
+        let date = resolve_date(arg, today);
+        if date.is_none() {
+            log_error!("[time-tracking] invalid date '{}'", arg);
+        }
+        date
+    };
+
+    let span = match args {
+        [] => None,
+        [date] => {
+            let Some(date) = resolve(date) else {
+                return Ok(());
+            };
+            Some((date, date))
+        }
+        [from, to] => {
+            let (Some(from), Some(to)) = (resolve(from), resolve(to)) else {
+                return Ok(());
+            };
+            Some(if from <= to { (from, to) } else { (to, from) })
+        }
+        _ => {
+            log_error!("[time-tracking] TimeTrackingProjects expects at most two dates");
+            return Ok(());
+        }
+    };
+
+    state::with(|s| s.view = View::Projects(span));
+    create_or_update_preview(&render_projects_view(config, span)?)
+}
+
+/// Render the per-project totals of the days in `span`, or with `None` of
+/// the current tracking buffer (today's file outside tracking files)
+pub fn render_projects_view(config: &'static Config, span: Option<(Date, Date)>) -> Result<String> {
+    let Some(pattern) = project_pattern() else {
+        return Ok(format!("# {}\n\n_{}_", label("projects"), label("no_data")));
+    };
+
+    let (title, contents) = match span {
+        None if is_time_tracking_file(config)? => {
+            // Prefer the live buffer so unsaved edits are counted
+            let date = current_buffer_date(config);
+            let title = match date {
+                Some(date) => format!("{} {}", label("projects"), heading_date(date, "%Y-%m-%d")),
+                None => label("projects"),
+            };
+            (title, vec![get_buffer_content()?])
+        }
+        None => day_contents(config, today()),
+        Some((from, to)) if from == to => day_contents(config, from),
+        Some((from, to)) => {
+            let title = format!(
+                "{} {} – {}",
+                label("projects"),
+                format_date(from, "%Y-%m-%d"),
+                format_date(to, "%Y-%m-%d")
+            );
+            let contents = days_between(from, to)
+                .filter_map(|date| load_day(config, date))
+                .collect();
+            (title, contents)
+        }
+    };
+
+    let mut projects = BTreeMap::new();
+    for content in &contents {
+        for (project, seconds) in seconds_by_project(content, &pattern) {
+            *projects.entry(project).or_insert(0) += seconds;
+        }
+    }
+
+    Ok(render_projects(&title, &projects))
+}
+
+/// The `project_tag_pattern`, or `None` (reported) if it isn't a valid regex
+fn project_pattern() -> Option<Regex> {
+    match Regex::new(&options::get().project_tag_pattern) {
+        Ok(pattern) => Some(pattern),
+        Err(e) => {
+            log_error!("[time-tracking] invalid project_tag_pattern: {}", e);
+            None
+        }
+    }
+}
+
+fn day_contents(config: &Config, date: Date) -> (String, Vec<String>) {
+    let title = format!("{} {}", label("projects"), heading_date(date, "%Y-%m-%d"));
+    (title, load_day(config, date).into_iter().collect())
+}
+
+/// The date of the current buffer's daily file, if its path matches the
+/// `daily_file_pattern`
+pub(crate) fn current_buffer_date(config: &Config) -> Option<Date> {
+    let name = api::get_current_buf().get_name().ok()?;
+    let path = std::fs::canonicalize(name).ok()?;
+    files::date_from_path(config, &utils::strip_verbatim_prefix(&path))
+}
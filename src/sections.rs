@@ -0,0 +1,17 @@
+use super::*;
+
+use crate::entries::with_section_subtotals;
+use crate::utils::{buffer_lines, set_changed_lines};
+
+/// Add or refresh a subtotal line at the end of each `##` section of the
+/// current tracking buffer, as one undoable change
+pub fn section_totals_fn(config: &'static Config) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let mut buf = api::get_current_buf();
+    let lines = buffer_lines(&buf)?;
+    let updated = with_section_subtotals(&lines);
+    set_changed_lines(&mut buf, &lines, &updated)
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+use nvim_oxi::api::opts::SetExtmarkOpts;
+
+use crate::utils::{namespace, preserving_view};
+
+/// Namespace holding the entry signs, so clearing never touches other
+/// plugins' signs
+const SIGN_NAMESPACE: &str = "time_tracking_signs";
+
+/// Turn the entry signs on or off
+pub fn toggle_signs_fn(config: &'static Config) -> Result<()> {
+    let enabled = state::with(|s| {
+        s.signs = !s.signs;
+        s.signs
+    });
+
+    if enabled {
+        update_signs_fn(config)
+    } else {
+        clear_all_signs()
+    }
+}
+
+/// Remove the entry signs from every buffer
+pub fn clear_all_signs() -> Result<()> {
+    let ns = namespace(SIGN_NAMESPACE);
+    for mut buf in api::list_bufs() {
+        buf.clear_namespace(ns, ..)?;
+    }
+    Ok(())
+}
+
+/// Place a sign on each line of the current tracking buffer that parses as a
+/// time entry
+pub fn update_signs_fn(config: &'static Config) -> Result<()> {
+    if !state::with(|s| s.signs) || !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    preserving_view(|| {
+        let day = parse_buffer_day()?;
+        let mut buf = api::get_current_buf();
+        let ns = namespace(SIGN_NAMESPACE);
+        buf.clear_namespace(ns, ..)?;
+
+        let options = options::get();
+        let opts = SetExtmarkOpts::builder()
+            .sign_text(&options.sign_text)
+            .sign_hl_group(options.sign_hl.as_str())
+            .build();
+        for entry in &day.entries {
+            buf.set_extmark(ns, entry.line, 0, &opts)?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Remove the entry signs from the current buffer
+pub fn clear_signs_fn() -> Result<()> {
+    api::get_current_buf().clear_namespace(namespace(SIGN_NAMESPACE), ..)
+}
@@ -0,0 +1,30 @@
+use super::*;
+
+use crate::entries::sort_entry_lines;
+use crate::utils::{buffer_lines, set_changed_lines};
+
+/// Sort the current tracking buffer's entries by duration within each
+/// section: longest first, or shortest first with `asc`. One undo restores
+/// the original order.
+pub fn sort_entries_fn(config: &'static Config, order: Option<&str>) -> Result<()> {
+    if !is_time_tracking_file(config)? {
+        return Ok(());
+    }
+
+    let ascending = match order.map(str::trim).unwrap_or("") {
+        "" | "desc" | "descending" => false,
+        "asc" | "ascending" => true,
+        other => {
+            log_error!(
+                "[time-tracking] invalid sort order '{}', expected asc or desc",
+                other
+            );
+            return Ok(());
+        }
+    };
+
+    let mut buf = api::get_current_buf();
+    let lines = buffer_lines(&buf)?;
+    let sorted = sort_entry_lines(&lines, ascending);
+    set_changed_lines(&mut buf, &lines, &sorted)
+}
@@ -0,0 +1,122 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use time::Date;
+
+/// What the preview is showing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    /// Summary of the current tracking buffer
+    #[default]
+    Day,
+    /// Aggregate of the daily files for a week
+    Week,
+    /// Per-day totals for an inclusive date range, from `TimeTrackingRange`
+    Range(Date, Date),
+    /// Month-by-month totals for this year, from `TimeTrackingYTD`
+    Ytd,
+    /// Two days side by side with per-task changes, from `TimeTrackingCompare`
+    Compare(Date, Date),
+    /// Per-project totals from `TimeTrackingProjects`, for an inclusive date
+    /// range or, with `None`, the current tracking buffer
+    Projects(Option<(Date, Date)>),
+}
+
+/// Seconds per task, keyed case-insensitively, with the first spelling
+pub type TaskSeconds = BTreeMap<String, (String, i64)>;
+
+/// Per-task durations of a source buffer at its last two refreshes that
+/// changed them, for `TimeTrackingDelta`
+#[derive(Debug, Clone)]
+pub struct TaskSnapshots {
+    /// Handle of the buffer the snapshots were taken from
+    pub source: i32,
+    pub previous: Option<TaskSeconds>,
+    pub latest: TaskSeconds,
+}
+
+/// The preview as it was when last closed, for `TimeTrackingUndoClose`
+#[derive(Debug, Clone)]
+pub struct ClosedPreview {
+    /// Handle of the buffer the content was rendered from
+    pub source: Option<i32>,
+    /// The preview's lines, joined with newlines
+    pub content: String,
+    pub view: View,
+    pub week_offset: i64,
+    pub paused: bool,
+}
+
+/// Runtime plugin state shared between commands and autocommands.
+///
+/// Neovim calls back into the plugin on its main thread only, so a
+/// thread-local is enough; keep borrows short and never call back into
+/// Neovim while holding one.
+#[derive(Debug, Default)]
+pub struct State {
+    /// Prefix set by `TimeTrackingSetPrefix`, overriding the config value
+    pub prefix: Option<String>,
+    /// Suffix set by `TimeTrackingSetSuffix`, overriding the config value
+    pub suffix: Option<String>,
+    /// While pinned, the preview isn't auto-closed when leaving tracking files
+    pub pinned: bool,
+    /// What the preview shows, which live updates re-render
+    pub view: View,
+    /// Week shown in week view, relative to the current week
+    pub week_offset: i64,
+    /// Whether the user wants the preview open, keyed by tabpage handle
+    pub tab_wants_preview: HashMap<i32, bool>,
+    /// Whether we've already told the user the terminal is too narrow
+    pub narrow_warned: bool,
+    /// While paused, live updates are skipped and the preview keeps its content
+    pub paused: bool,
+    /// Generation of the most recently requested preview render
+    pub render_generation: u64,
+    /// Whether entry lines are marked in the sign column
+    pub signs: bool,
+    /// Source buffer handle and preview line -> source line for the entries
+    /// in the day view, used by `TimeTrackingGotoEntry`
+    pub entry_lines: Option<(i32, HashMap<usize, usize>)>,
+    /// Window to return to from the preview on `TimeTrackingPreviewBlur`
+    pub focus_return: Option<i32>,
+    /// Width of the last closed preview, reused when it's reopened
+    pub preview_width: Option<u32>,
+    /// The CLI config's `noedit`: harden the preview against edits
+    pub noedit: bool,
+    /// Source windows scroll-bound to the preview, keyed by tabpage handle
+    pub scrollbound: HashMap<i32, i32>,
+    /// A preview resize is scheduled after a window layout change
+    pub resize_pending: bool,
+    /// Buffer the preview content was last rendered from
+    pub rendered_from: Option<i32>,
+    /// Snapshot of the last closed preview, until another file is entered
+    pub closed_preview: Option<ClosedPreview>,
+    /// Show "Today"/"Yesterday" instead of dates in plugin-generated headings
+    pub relative_dates: bool,
+    /// Output line showing an over-budget total, highlighted by the next
+    /// preview write
+    pub budget_line: Option<String>,
+    /// Source buffers whose over-budget warning was already shown
+    pub budget_notified: HashSet<i32>,
+    /// While the preview is zoomed, the `(window, width, height)` of every
+    /// window in the zoomed tabpage beforehand
+    pub zoom_layout: Option<Vec<(i32, u32, u32)>>,
+    /// Task minutes as of the day view's recent refreshes
+    pub task_snapshots: Option<TaskSnapshots>,
+    /// Whether plugin-computed durations include seconds (`show_seconds`)
+    pub show_seconds: bool,
+    /// Whether inline durations and the winbar total are shown
+    /// (`mode = "inline"`)
+    pub inline: bool,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Run `f` with mutable access to the shared state
+pub fn with<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
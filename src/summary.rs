@@ -0,0 +1,107 @@
+use std::borrow::Cow;
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, IntoDeserializer, Visitor, value},
+    forward_to_deserialize_any,
+};
+use time_tracking_cli::{Config, config::Formatter};
+
+use crate::options::{self, TotalPosition};
+
+/// Format `content` as a day summary using `config`'s prefix and suffix.
+///
+/// Pure: no buffers or windows are touched, so this can be used headless
+/// (e.g. snapshot tests in CI) and gives the same output as the preview.
+pub fn summarize(content: &str, config: &Config) -> String {
+    summarize_with(content, config, config.get_prefix(), config.get_suffix())
+}
+
+/// Like [`summarize`], with an explicit prefix and suffix
+pub fn summarize_with(content: &str, config: &Config, prefix: &str, suffix: &str) -> String {
+    config
+        .get_formatter()
+        .day_summary(content, "", prefix, suffix)
+}
+
+/// Parse a formatter by the name it has in the CLI's config file (e.g. `Default`)
+pub fn parse_formatter(name: &str) -> Option<Formatter> {
+    let deserializer: value::StrDeserializer<'_, value::Error> = name.into_deserializer();
+    Formatter::deserialize(deserializer).ok()
+}
+
+/// Names of the formatters the CLI supports, as its config file spells them.
+/// Read off the CLI's `Formatter` deserializer, so new formatters show up
+/// without changes here.
+pub fn formatter_names() -> &'static [&'static str] {
+    let mut capture = VariantCapture::default();
+    let _ = Formatter::deserialize(&mut capture);
+    capture.variants
+}
+
+/// A deserializer that records the variant names an enum asks for, then fails
+#[derive(Default)]
+struct VariantCapture {
+    variants: &'static [&'static str],
+}
+
+impl<'de> Deserializer<'de> for &mut VariantCapture {
+    type Error = value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only enum variants are captured"))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.variants = variants;
+        Err(de::Error::custom("variants captured"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// `config` with its formatter replaced by the one the `formatters` option
+/// maps `extension` to. Unmapped extensions and unknown formatter names keep
+/// the global formatter.
+pub fn config_for_extension<'a>(config: &'a Config, extension: Option<&str>) -> Cow<'a, Config> {
+    let formatter = extension
+        .and_then(|ext| options::get().formatters.get(ext).cloned())
+        .and_then(|name| parse_formatter(&name));
+    match formatter {
+        Some(formatter) => Cow::Owned(Config {
+            formatter: Some(formatter),
+            ..config.clone()
+        }),
+        None => Cow::Borrowed(config),
+    }
+}
+
+/// Add a computed footer (e.g. the grand total) to rendered content, at the
+/// bottom or, per the `total_position` option, at the top just under the
+/// leading `#` heading if there is one
+pub fn with_footer(body: &str, footer: &str) -> String {
+    match options::get().total_position {
+        TotalPosition::Bottom => format!("{}\n\n{}", body, footer),
+        TotalPosition::Top => match body.split_once('\n') {
+            Some((heading, rest)) if heading.starts_with('#') => {
+                format!(
+                    "{}\n\n{}\n\n{}",
+                    heading,
+                    footer,
+                    rest.trim_start_matches('\n')
+                )
+            }
+            _ if body.starts_with('#') => format!("{}\n\n{}", body, footer),
+            _ => format!("{}\n\n{}", footer, body),
+        },
+    }
+}
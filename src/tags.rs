@@ -0,0 +1,114 @@
+use super::*;
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::entries::{ParsedDay, format_precise, total_seconds};
+use crate::labels::label;
+
+/// The tags on an entry's line, per `pattern`: the first capture group of each
+/// match (or the whole match if it has no groups), without repeats
+pub fn line_tags(line: &str, pattern: &Regex) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for caps in pattern.captures_iter(line) {
+        if let Some(tag) = caps.get(1).or_else(|| caps.get(0)) {
+            let tag = tag.as_str().trim().to_owned();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Render `day`'s entries under a `## tag (subtotal)` heading per tag,
+/// sorted by tag, with untagged entries last. An entry with several tags is
+/// listed and counted under each, noting the others.
+pub fn render_tag_groups(day: &ParsedDay, pattern: &Regex) -> String {
+    let mut groups: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+    let mut subtotals: BTreeMap<Option<String>, i64> = BTreeMap::new();
+
+    for entry in &day.entries {
+        let tags = line_tags(&day.lines[entry.line], pattern);
+        let row = format!(
+            "- {}: {}",
+            entry.description,
+            format_precise(entry.duration_seconds())
+        );
+        if tags.is_empty() {
+            groups.entry(None).or_default().push(row);
+            *subtotals.entry(None).or_insert(0) += entry.duration_seconds();
+            continue;
+        }
+
+        for tag in &tags {
+            let others: Vec<&str> = tags
+                .iter()
+                .filter(|other| *other != tag)
+                .map(String::as_str)
+                .collect();
+            let row = match others.as_slice() {
+                [] => row.clone(),
+                others => format!("{} ({} {})", row, label("also"), others.join(", ")),
+            };
+            groups.entry(Some(tag.clone())).or_default().push(row);
+            *subtotals.entry(Some(tag.clone())).or_insert(0) += entry.duration_seconds();
+        }
+    }
+
+    if groups.is_empty() {
+        return format!("_{}_", label("no_data"));
+    }
+
+    // `None` sorts first; untagged goes last
+    let mut sections: Vec<(Option<String>, Vec<String>)> = groups.into_iter().collect();
+    sections.sort_by_key(|(tag, _)| tag.is_none());
+    sections
+        .into_iter()
+        .map(|(tag, rows)| {
+            let subtotal = format_precise(subtotals[&tag]);
+            let name = tag.unwrap_or_else(|| label("untagged"));
+            format!("## {} ({})\n{}", name, subtotal, rows.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// The formatter's `summary` reorganized by tag: its leading heading, if any,
+/// then [`render_tag_groups`] and the day's total (left to the billable
+/// footer when there is one). Unchanged with an invalid `tag_pattern`, which
+/// is reported at setup.
+pub fn group_summary_by_tag(summary: &str, day: &ParsedDay) -> String {
+    let options = options::get();
+    let Ok(pattern) = Regex::new(&options.tag_pattern) else {
+        return summary.to_owned();
+    };
+
+    let groups = render_tag_groups(day, &pattern);
+    let body = match summary.lines().next().filter(|line| line.starts_with('#')) {
+        Some(heading) => format!("{}\n\n{}", heading, groups),
+        None => groups,
+    };
+    if options.billable_marker.is_some() {
+        return body;
+    }
+
+    let total = total_seconds(&day.entries);
+    with_footer(
+        &body,
+        &format!("{}: {}", label("total"), format_precise(total)),
+    )
+}
+
+/// Warn at setup when `group_by_tag` is on but `tag_pattern` isn't a valid
+/// regex, rather than silently showing the ungrouped summary
+pub fn validate_tag_pattern() {
+    let options = options::get();
+    if !options.group_by_tag {
+        return;
+    }
+    if let Err(e) = Regex::new(&options.tag_pattern) {
+        log_warn!("[time-tracking] invalid tag_pattern: {}", e);
+    }
+}
@@ -1,11 +1,19 @@
-use std::{fs, path::Path};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    path::{Component, Path, PathBuf, Prefix},
+};
 
 use nvim_oxi::{
-    Result,
-    api::{self, Buffer, Error, Window},
+    Array, Dictionary, Object, Result,
+    api::{self, Buffer, TabPage, Window, opts::OptionOptsBuilder},
 };
 use time_tracking_cli::Config;
 
+use crate::options::{self, PreviewScope};
+
 /// Check if the current buffer is a time tracking file (markdown file in data directory)
 pub fn is_time_tracking_file(config: &Config) -> Result<bool> {
     let current_buffer = api::get_current_buf();
@@ -19,67 +27,229 @@ pub fn is_win_time_tracking_file(win: Window, config: &Config) -> Result<bool> {
 }
 
 /// Checks if the provided buffer is a time tracking file (markdown file in data directory)
+///
+/// This runs on every keystroke, so the data directory and each buffer's
+/// verdict are cached; see [`clear_detection_cache`].
 pub fn is_buf_time_tracking_file(current_buffer: Buffer, config: &Config) -> Result<bool> {
+    // Buffer names are kept as OS paths throughout, so names that aren't valid
+    // UTF-8 still canonicalize and compare correctly; only messages are lossy.
     let buffer_name = current_buffer.get_name()?;
 
     if buffer_name.as_os_str().is_empty() {
         return Ok(false);
     }
 
-    let buffer_path = Path::new(&buffer_name);
-    let buffer_path = fs::canonicalize(buffer_path)
-        .map_err(|e| {
-            Error::Other(format!(
-                "Could not convert {} to a path: {}",
-                buffer_name.display(),
-                e
-            ))
-        })
-        .ok();
-
-    if buffer_path.is_none() {
+    // Terminal, quickfix, help and scratch buffers have pseudo-names; skip
+    // them before touching the filesystem
+    let opts = OptionOptsBuilder::default()
+        .buffer(current_buffer.clone())
+        .build();
+    if !api::get_option_value::<String>("buftype", &opts)?.is_empty() {
         return Ok(false);
     }
 
-    // TODO: Need to canonicalize in case the data directory is a symlink, should be done upstream
-    // probably
-    let data_dir = fs::canonicalize(config.get_data_directory().unwrap_or(""))
-        .map_err(|_| Error::Other("could not find path for data directory".to_owned()))
-        .ok();
-
-    if buffer_path.is_none() || data_dir.is_none() {
+    let Some(data_dir) = cached_data_dir(config) else {
         return Ok(false);
+    };
+
+    // A verdict is only valid for the name it was computed for, so renaming
+    // the buffer (`:file`, `:saveas`) invalidates it
+    let handle = current_buffer.handle();
+    let cached = VERDICT_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .get(&handle)
+            .filter(|(name, _)| *name == buffer_name)
+            .map(|(_, verdict)| *verdict)
+    });
+    if let Some(verdict) = cached {
+        return Ok(verdict);
     }
 
-    let buffer_path = buffer_path.unwrap();
-    let data_dir = data_dir.unwrap();
+    // Not cached when this fails: the file may simply not be written yet
+    let Ok(buffer_path) = fs::canonicalize(Path::new(&buffer_name)) else {
+        return Ok(false);
+    };
+    let buffer_path = strip_verbatim_prefix(&buffer_path);
 
-    // Check if file is in data directory and has .md extension
+    // Check if file is in data directory and has a tracking extension
     let is_time_tracking_file = buffer_path.starts_with(data_dir)
-        && matches!(buffer_path.extension(), Some(ext) if ext == "md");
+        && matches!(buffer_path.extension(), Some(ext)
+            if options::get().is_tracking_extension(&ext.to_string_lossy()));
+
+    VERDICT_CACHE.with(|cache| {
+        cache
+            .borrow_mut()
+            .insert(handle, (buffer_name, is_time_tracking_file))
+    });
 
     Ok(is_time_tracking_file)
 }
 
-/// Get the content of the current buffer
+thread_local! {
+    /// The canonicalized data directory, along with the configured path it was
+    /// resolved from
+    static DATA_DIR_CACHE: RefCell<Option<(Option<String>, Option<PathBuf>)>> =
+        const { RefCell::new(None) };
+    /// Tracking-file verdicts by buffer handle, with the name they were
+    /// computed for
+    static VERDICT_CACHE: RefCell<HashMap<i32, (PathBuf, bool)>> = RefCell::new(HashMap::new());
+}
+
+/// The configured data directory, canonicalized, if it exists
+///
+/// Resolved once and reused until the configured path changes or
+/// [`clear_detection_cache`] is called.
+pub fn cached_data_dir(config: &Config) -> Option<PathBuf> {
+    let configured = config.get_data_directory();
+    let cached = DATA_DIR_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .as_ref()
+            .filter(|(path, _)| path.as_deref() == configured)
+            .map(|(_, dir)| dir.clone())
+    });
+    if let Some(dir) = cached {
+        return dir;
+    }
+
+    // Canonicalize in case the data directory is a symlink
+    let dir = configured
+        .and_then(|path| fs::canonicalize(expand_tilde(path)).ok())
+        .map(|path| strip_verbatim_prefix(&path));
+
+    // A file here would make every `starts_with` check meaningless, so turn
+    // detection off instead. Cached, so this only warns once per resolution.
+    let dir = match dir {
+        Some(dir) if !dir.is_dir() => {
+            crate::log_warn!(
+                "[time-tracking] data_directory {} is not a directory; tracking file detection is off",
+                dir.display()
+            );
+            None
+        }
+        dir => dir,
+    };
+
+    DATA_DIR_CACHE
+        .with(|cache| *cache.borrow_mut() = Some((configured.map(str::to_owned), dir.clone())));
+    // Verdicts were computed against the old directory
+    VERDICT_CACHE.with(|cache| cache.borrow_mut().clear());
+
+    dir
+}
+
+/// Expand a leading `~` to the user's home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    match (path.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            let mut expanded = PathBuf::from(home);
+            expanded.push(rest.trim_start_matches(['/', '\\']));
+            expanded
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Forget the cached data directory and tracking-file verdicts
+pub fn clear_detection_cache() {
+    DATA_DIR_CACHE.with(|cache| cache.borrow_mut().take());
+    VERDICT_CACHE.with(|cache| cache.borrow_mut().clear());
+}
+
+/// Strip the verbatim (`\\?\`) prefix `fs::canonicalize` adds on Windows, so
+/// `\\?\C:\notes` compares equal to `C:\notes` and `\\?\UNC\server\share`
+/// to `\\server\share`. Paths without such a prefix are returned unchanged.
+pub fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_path_buf();
+    };
+
+    let plain_prefix = match prefix.kind() {
+        Prefix::VerbatimDisk(drive) => OsString::from(format!("{}:", drive as char)),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            unc
+        }
+        _ => return path.to_path_buf(),
+    };
+
+    let mut normalized = PathBuf::from(plain_prefix);
+    normalized.push(components.as_path());
+    normalized
+}
+
+thread_local! {
+    /// Buffers we've already warned about containing invalid UTF-8
+    static NON_UTF8_WARNED: RefCell<HashSet<i32>> = RefCell::new(HashSet::new());
+}
+
+/// Forget which buffers were warned about invalid UTF-8, so they're warned
+/// about again
+pub fn clear_non_utf8_warnings() {
+    NON_UTF8_WARNED.with(|warned| warned.borrow_mut().clear());
+}
+
+/// Get the content of the current buffer, joining lines with the
+/// `content_join` option (`\n` by default), trimmed of surrounding blank
+/// lines and whitespace when `trim_content` is set
 pub fn get_buffer_content() -> Result<String> {
+    let options = crate::options::get();
+    let content = get_buffer_content_with(&options.content_join)?;
+    Ok(if options.trim_content {
+        content.trim().to_owned()
+    } else {
+        content
+    })
+}
+
+/// Get the content of the current buffer, joining lines with `join`
+///
+/// Lines that aren't valid UTF-8 are converted lossily (invalid bytes become
+/// U+FFFD) and a warning is shown once per buffer, rather than failing.
+pub fn get_buffer_content_with(join: &str) -> Result<String> {
     let current_buffer = api::get_current_buf();
     let line_count = current_buffer.line_count()?;
     let lines = current_buffer.get_lines(0..line_count, false)?;
-    Ok(lines
+
+    let mut had_invalid_utf8 = false;
+    let content = lines
         .into_iter()
-        .map(|s| s.to_string())
+        .map(|s| match std::str::from_utf8(s.as_bytes()) {
+            Ok(line) => line.to_owned(),
+            Err(_) => {
+                had_invalid_utf8 = true;
+                s.to_string_lossy().into_owned()
+            }
+        })
         .collect::<Vec<String>>()
-        .join("\n"))
+        .join(join);
+
+    if had_invalid_utf8 {
+        let first_warning =
+            NON_UTF8_WARNED.with(|warned| warned.borrow_mut().insert(current_buffer.handle()));
+        if first_warning {
+            crate::log_warn!(
+                "[time-tracking] buffer {} contains invalid UTF-8; invalid bytes were replaced",
+                current_buffer.handle()
+            );
+        }
+    }
+
+    Ok(content)
 }
 
 pub fn any_tracking_visible(config: &Config) -> Result<bool> {
     for win in api::list_wins() {
         let buf = win.get_buf()?;
-        let name = buf.get_name()?;
 
         // Skip the preview itself
-        if name.ends_with("[Time Tracking Preview]") {
+        if is_preview_buffer(&buf)? {
             continue;
         }
 
@@ -92,3 +262,154 @@ pub fn any_tracking_visible(config: &Config) -> Result<bool> {
     }
     Ok(false)
 }
+
+/// Buffer-local variable set on the preview buffer when it's created
+pub const PREVIEW_VAR: &str = "time_tracking_preview";
+
+/// Buffer-local variable holding the key of the context a preview buffer
+/// belongs to, see [`preview_key`]
+pub const PREVIEW_KEY_VAR: &str = "time_tracking_preview_key";
+
+/// Key of the preview the current context renders into, per `preview_scope`:
+/// 0 for the global preview, otherwise the tabpage or source window handle
+pub fn preview_key() -> Result<i32> {
+    Ok(match options::get().preview_scope {
+        PreviewScope::Global => 0,
+        PreviewScope::Tab => api::get_current_tabpage().handle(),
+        PreviewScope::Window => {
+            let win = api::get_current_win();
+            let buf = win.get_buf()?;
+            // From inside a preview, keep addressing that preview
+            if has_preview_var(&buf) {
+                preview_key_of(&buf)
+            } else {
+                win.handle()
+            }
+        }
+    })
+}
+
+fn preview_key_of(buf: &Buffer) -> i32 {
+    buf.get_var::<i32>(PREVIEW_KEY_VAR).unwrap_or(0)
+}
+
+/// Checks if the provided buffer carries the preview marker variable
+pub fn has_preview_var(buf: &Buffer) -> bool {
+    buf.get_var::<bool>(PREVIEW_VAR).unwrap_or(false)
+}
+
+/// Checks if the provided buffer is the time tracking preview buffer
+///
+/// Goes by the marker variable rather than the name, which is configurable
+/// (`preview_name`) and could be shared by an unrelated buffer.
+pub fn is_preview_buffer(buf: &Buffer) -> Result<bool> {
+    Ok(has_preview_var(buf))
+}
+
+/// Find the window displaying the current context's preview buffer (see
+/// [`preview_key`]) in the current tabpage, if any. With the "window" scope
+/// each source window has a preview window of its own.
+pub fn find_preview_window() -> Result<Option<Window>> {
+    let key = preview_key()?;
+    for win in api::get_current_tabpage().list_wins()? {
+        let buf = win.get_buf()?;
+        if is_preview_buffer(&buf)? && preview_key_of(&buf) == key {
+            return Ok(Some(win));
+        }
+    }
+    Ok(None)
+}
+
+/// Find a window displaying any preview buffer in `tabpage`, if any
+pub fn find_preview_window_in(tabpage: &TabPage) -> Result<Option<Window>> {
+    for win in tabpage.list_wins()? {
+        if is_preview_buffer(&win.get_buf()?)? {
+            return Ok(Some(win));
+        }
+    }
+    Ok(None)
+}
+
+/// Find every window displaying the preview buffer, across all tabpages
+pub fn find_preview_windows() -> Result<Vec<Window>> {
+    let mut windows = Vec::new();
+    for win in api::list_wins() {
+        if is_preview_buffer(&win.get_buf()?)? {
+            windows.push(win);
+        }
+    }
+    Ok(windows)
+}
+
+/// Find the current context's preview buffer (see [`preview_key`]), whether
+/// or not it is currently displayed
+pub fn find_preview_buffer() -> Result<Option<Buffer>> {
+    let key = preview_key()?;
+    for buf in api::list_bufs() {
+        if is_preview_buffer(&buf)? && preview_key_of(&buf) == key {
+            return Ok(Some(buf));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether a macro is currently being recorded or executed
+pub fn macro_active() -> bool {
+    ["reg_recording", "reg_executing"].iter().any(|func| {
+        api::call_function::<_, String>(func, Array::new())
+            .map(|register| !register.is_empty())
+            .unwrap_or(false)
+    })
+}
+
+/// The id of the plugin's extmark namespace `name`, created on first use
+pub fn namespace(name: &str) -> u32 {
+    // Returns the existing id when the namespace was already created
+    api::create_namespace(name)
+}
+
+/// The lines of `buf`, converted lossily if they aren't valid UTF-8
+pub fn buffer_lines(buf: &Buffer) -> Result<Vec<String>> {
+    Ok(buf
+        .get_lines(0..buf.line_count()?, false)?
+        .map(|line| line.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Replace `buf`'s `lines` with `updated`, rewriting only the span between
+/// the first and last differing lines. It's a single edit, so one undo
+/// reverts it, and marks and folds outside the span are left alone.
+pub fn set_changed_lines(buf: &mut Buffer, lines: &[String], updated: &[String]) -> Result<()> {
+    let prefix = lines
+        .iter()
+        .zip(updated)
+        .take_while(|(old, new)| old == new)
+        .count();
+    if prefix == lines.len() && prefix == updated.len() {
+        return Ok(());
+    }
+    let suffix = lines[prefix..]
+        .iter()
+        .rev()
+        .zip(updated[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    buf.set_lines(
+        prefix..lines.len() - suffix,
+        false,
+        updated[prefix..updated.len() - suffix]
+            .iter()
+            .map(String::as_str),
+    )
+}
+
+/// Run `f` with the current window's view (cursor, scroll position) saved
+/// and restored around it, so refreshing annotations on a source buffer never
+/// makes it jump
+pub fn preserving_view<R>(f: impl FnOnce() -> Result<R>) -> Result<R> {
+    let view: Dictionary = api::call_function("winsaveview", Array::new())?;
+    let result = f();
+    api::call_function::<_, Object>("winrestview", (view,))?;
+    result
+}